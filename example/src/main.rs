@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
-use terraform::{Terraform, Error, TerraformResourceStatus};
+use terraform::{Error, ResourceTracker, Terraform};
 
 fn main() -> Result<(), Error> {
     let (sender, receiver) = channel();
@@ -25,32 +25,20 @@ fn main() -> Result<(), Error> {
         terraform.run_destroy().unwrap();
     });
 
-    let mut plan_modifications = 0;
-    let mut apply_running = 0;
-    let mut apply_done = 0;
-    let mut destroy_running = 0;
-    let mut destroy_done = 0;
+    let mut apply = ResourceTracker::new();
+    let mut destroy = ResourceTracker::new();
 
     while let Ok(event) = receiver.recv() {
-        if event.command == "plan" && event.status == Some(TerraformResourceStatus::Completed) {
-            plan_modifications = event.create_count.unwrap_or(0) + event.update_count.unwrap_or(0) + event.delete_count.unwrap_or(0)
+        if event.command == "plan" {
+            apply.track(&event);
         } else if event.command == "apply" {
-            if event.status == Some(TerraformResourceStatus::Started) {
-                apply_running += 1;
-                println!("apply - total: {} | running: {} | done: {}", plan_modifications, apply_running, apply_done);
-            } else if event.status == Some(TerraformResourceStatus::Done) {
-                apply_running -= 1;
-                apply_done += 1;
-                println!("apply - total: {} | running: {} | done: {}", plan_modifications, apply_running, apply_done);
+            if apply.track(&event) {
+                println!("apply - total: {} | running: {} | done: {}", apply.total(), apply.running(), apply.done());
             }
         } else if event.command == "destroy" {
-            if event.status == Some(TerraformResourceStatus::Started) {
-                destroy_running += 1;
-                println!("destroy - total: {} | running: {} | done: {}", apply_done, destroy_running, destroy_done);
-            } else if event.status == Some(TerraformResourceStatus::Done) {
-                destroy_running -= 1;
-                destroy_done += 1;
-                println!("destroy - total: {} | running: {} | done: {}", apply_done, destroy_running, destroy_done);
+            destroy.set_total(apply.done());
+            if destroy.track(&event) {
+                println!("destroy - total: {} | running: {} | done: {}", destroy.total(), destroy.running(), destroy.done());
             }
         }
     }
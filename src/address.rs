@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+// A resource instance address split into its components, e.g. `module.x["key"].aws_instance.y[0]` has the module
+// path `["module.x[\"key\"]"]`, the type `aws_instance`, the name `y` and the index key `0`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ResourceAddress {
+    // Module instances from the root module, as written in the address.
+    pub module_path: Vec<String>,
+    // Set for data sources (`data.` addresses).
+    #[serde(default)]
+    pub data_source: bool,
+    pub resource_type: String,
+    pub resource_name: String,
+    // The `count` index or `for_each` key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub index_key: Option<ResourceIndexKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ResourceIndexKey {
+    Int(u64),
+    String(String),
+}
+
+impl ResourceAddress {
+    // Returns `None` when `address` isn't a resource instance address (e.g. a module address or an output).
+    pub fn parse(address: &str) -> Option<Self> {
+        let mut module_path = Vec::new();
+        let mut rest = address;
+
+        while let Some(module) = rest.strip_prefix("module.") {
+            let (name, after_name) = split_name(module);
+            let (_, after_index) = split_index_key(after_name)?;
+            if name.is_empty() {
+                return None;
+            }
+
+            module_path.push(String::from(&rest[..rest.len() - after_index.len()]));
+            rest = after_index.strip_prefix('.')?;
+        }
+
+        let data_source = rest.starts_with("data.");
+        if data_source {
+            rest = &rest["data.".len()..];
+        }
+
+        let (resource_type, after_type) = split_name(rest);
+        let (resource_name, after_name) = split_name(after_type.strip_prefix('.')?);
+        let (index_key, after_index) = split_index_key(after_name)?;
+        if resource_type.is_empty() || resource_name.is_empty() || !after_index.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            module_path,
+            data_source,
+            resource_type: String::from(resource_type),
+            resource_name: String::from(resource_name),
+            index_key,
+        })
+    }
+}
+
+fn split_name(address: &str) -> (&str, &str) {
+    let end = address.find(['.', '[']).unwrap_or(address.len());
+    address.split_at(end)
+}
+
+// Splits an optional `[0]` or `["key"]` from the start of `address`, `None` meaning it is malformed.
+fn split_index_key(address: &str) -> Option<(Option<ResourceIndexKey>, &str)> {
+    let Some(index) = address.strip_prefix('[') else {
+        return Some((None, address));
+    };
+
+    if let Some(quoted) = index.strip_prefix('"') {
+        let mut key = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((position, c)) = chars.next() {
+            match c {
+                '\\' => key.push(chars.next()?.1),
+                '"' => {
+                    let rest = quoted[position + 1..].strip_prefix(']')?;
+                    return Some((Some(ResourceIndexKey::String(key)), rest));
+                }
+                c => key.push(c),
+            }
+        }
+        None
+    } else {
+        let (key, rest) = index.split_once(']')?;
+        Some((Some(ResourceIndexKey::Int(key.parse().ok()?)), rest))
+    }
+}
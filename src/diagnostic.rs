@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+// A "diagnostic" message of Terraform's machine readable UI (`-json`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TerraformDiagnostic {
+    pub severity: TerraformDiagnosticSeverity,
+    pub summary: String,
+    #[serde(default)]
+    pub detail: String,
+    // Resource the diagnostic relates to, e.g. a failed resource of apply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub address: Option<String>,
+    // Location in the configuration, when the diagnostic relates to one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub range: Option<TerraformDiagnosticRange>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum TerraformDiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TerraformDiagnosticRange {
+    // Relative to the working directory.
+    pub filename: String,
+    pub start: TerraformDiagnosticPosition,
+    pub end: TerraformDiagnosticPosition,
+}
+
+// Lines and columns start at 1, the byte offset at 0.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TerraformDiagnosticPosition {
+    pub line: u32,
+    pub column: u32,
+    pub byte: u64,
+}
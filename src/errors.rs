@@ -1,11 +1,30 @@
 use crate::process;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum Error {
     PathError,
-    IOError(String),
+    // The kind of the original error, then its message.
+    IOError(std::io::ErrorKind, String),
     RegexError(String),
+    JsonError(String),
     ProcessError(process::Error),
+    CommandFailed(Option<i32>),
+    OperationForbidden,
+    ApprovalDenied,
+    // Approval isn't automatic, but Terraform's confirmation prompt can't be answered in this mode.
+    ApprovalUnsupported,
+    PlanFileMissing(PathBuf),
+    PlanCorrupt(PathBuf),
+    BinaryUnavailable(PathBuf, String),
+    NotAConfiguration(PathBuf),
+    // The installed version, then the configuration constraint it doesn't satisfy.
+    UnsupportedVersion(String, String),
+    InvalidWorkspaceName(String),
+    // Addresses of the resources the plan would destroy or replace.
+    DestructivePlan(Vec<String>),
+    // Options of a request that Terraform doesn't accept together.
+    InvalidRequest(String),
 }
 
 impl From<regex::Error> for Error {
@@ -14,9 +33,15 @@ impl From<regex::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::JsonError(e.to_string())
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Error {
-        Error::IOError(e.to_string())
+        Error::IOError(e.kind(), e.to_string())
     }
 }
 
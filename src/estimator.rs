@@ -0,0 +1,108 @@
+use crate::event::{TerraformEvent, TerraformResourceChange, TerraformResourceStatus};
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Estimates the remaining apply time from the elapsed time of completed resources, averaged per change type.
+// Resources not started yet are assumed to run `parallelism` at a time, as Terraform does (10 by default).
+#[derive(Debug)]
+pub struct ApplyEstimator {
+    averages: HashMap<TerraformResourceChange, (Duration, u32)>,
+    running: HashMap<String, (Option<TerraformResourceChange>, Duration)>,
+    total: u32,
+    finished: u32,
+    parallelism: u32,
+}
+
+impl Default for ApplyEstimator {
+    fn default() -> Self {
+        Self {
+            averages: HashMap::new(),
+            running: HashMap::new(),
+            total: 0,
+            finished: 0,
+            parallelism: 10,
+        }
+    }
+}
+
+impl ApplyEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_parallelism(&mut self, parallelism: u32) {
+        self.parallelism = parallelism.max(1);
+    }
+
+    pub fn set_total(&mut self, total: u32) {
+        self.total = total;
+    }
+
+    pub fn track(&mut self, event: &TerraformEvent) {
+        let change = event.change.first().copied();
+
+        match (event.status, event.resource_path.as_ref()) {
+            (Some(TerraformResourceStatus::Completed), _) if event.command == "plan" => {
+                self.total =
+                    event.create_count.unwrap_or(0) + event.update_count.unwrap_or(0) + event.delete_count.unwrap_or(0);
+            }
+            (Some(TerraformResourceStatus::Started), Some(address)) => {
+                self.running.insert(address.clone(), (change, Duration::default()));
+            }
+            (Some(TerraformResourceStatus::InProgress), Some(address)) => {
+                if let (Some(running), Some(elapsed)) = (self.running.get_mut(address), event.elapsed) {
+                    running.1 = elapsed;
+                }
+            }
+            (Some(TerraformResourceStatus::Done), Some(address)) => {
+                self.running.remove(address);
+                self.finished += 1;
+
+                if let (Some(change), Some(elapsed)) = (change, event.elapsed) {
+                    let (sum, count) = self.averages.entry(change).or_default();
+                    *sum += elapsed;
+                    *count += 1;
+                }
+            }
+            (Some(TerraformResourceStatus::Failed), Some(address)) => {
+                self.running.remove(address);
+                self.finished += 1;
+            }
+            _ => {}
+        }
+    }
+
+    // `None` until at least one resource completed with a known elapsed time.
+    pub fn estimated_remaining(&self) -> Option<Duration> {
+        let (sum, count) = self
+            .averages
+            .values()
+            .fold((Duration::default(), 0), |(sum, count), (s, c)| (sum + *s, count + c));
+        let overall = sum.checked_div(count)?;
+
+        let running = self
+            .running
+            .values()
+            .map(|(change, elapsed)| {
+                change
+                    .and_then(|change| self.average(change))
+                    .unwrap_or(overall)
+                    .saturating_sub(*elapsed)
+            })
+            .max()
+            .unwrap_or_default();
+
+        let pending = self
+            .total
+            .saturating_sub(self.finished)
+            .saturating_sub(self.running.len() as u32);
+
+        Some(running + overall * pending / self.parallelism)
+    }
+
+    fn average(&self, change: TerraformResourceChange) -> Option<Duration> {
+        let (sum, count) = self.averages.get(&change)?;
+
+        sum.checked_div(*count)
+    }
+}
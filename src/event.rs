@@ -1,5 +1,11 @@
+use crate::address::ResourceAddress;
+use chrono::{DateTime, FixedOffset};
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "numeric-source-stream")]
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TerraformEvent {
@@ -12,12 +18,21 @@ pub struct TerraformEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub resource_path: Option<String>,
+    // `resource_path` split into its components, when it is a resource instance address.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
+    pub parsed_address: Option<ResourceAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[deprecated(note = "use `attributes` instead")]
     pub id_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
+    #[deprecated(note = "use `attributes` instead")]
     pub id_value: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub create_count: Option<u32>,
@@ -27,22 +42,82 @@ pub struct TerraformEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub delete_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub import_count: Option<u32>,
+    // From "Still creating... [10s elapsed]" and "Creation complete after 10s".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub elapsed: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub generated_config: Option<PathBuf>,
+    // Set on the event sent once apply completes, when the configuration has outputs.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default)]
+    pub outputs: HashMap<String, String>,
+    // "@timestamp" and "@level" of the message, in JSON mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub level: Option<TerraformLogLevel>,
+    // How to resolve the error reported by the event, when it is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub remediation: Option<TerraformRemediation>,
+    // Set on the targeting warning of a plan: the plan doesn't include every change of the configuration.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    #[serde(default)]
+    pub partial_plan: bool,
+    // Set when the line matched one of the known patterns or is an interpreted JSON message, and on the events built
+    // by the crate (outputs, notices); unset on plain output that is only forwarded.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    #[serde(default)]
+    pub matched: bool,
+    // Set on the `Exited` event ending an async stream; `None` when Terraform was killed by a signal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    // Identifier of the `TerraformPool` stack the event comes from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub stack: Option<String>,
     pub command: String,
     pub source: String,
     pub source_stream: TerraformSourceStream,
 }
 
+#[allow(deprecated)]
 impl Default for TerraformEvent {
     fn default() -> Self {
         Self {
             change: Vec::new(),
             status: None,
             resource_path: None,
+            parsed_address: None,
             id_key: None,
             id_value: None,
+            attributes: HashMap::new(),
             create_count: None,
             update_count: None,
             delete_count: None,
+            import_count: None,
+            elapsed: None,
+            message: None,
+            generated_config: None,
+            outputs: HashMap::new(),
+            timestamp: None,
+            level: None,
+            remediation: None,
+            partial_plan: false,
+            matched: false,
+            exit_code: None,
+            stack: None,
             command: String::new(),
             source: String::new(),
             source_stream: TerraformSourceStream::Stdout,
@@ -50,7 +125,23 @@ impl Default for TerraformEvent {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+impl TerraformEvent {
+    pub fn is_destructive(&self) -> bool {
+        self.change
+            .iter()
+            .any(|change| *change == TerraformResourceChange::Destroy || *change == TerraformResourceChange::Replace)
+    }
+
+    // Fills `parsed_address` from `resource_path`, unless it was already set (e.g. by a user line parser).
+    pub(crate) fn with_parsed_address(mut self) -> Self {
+        if self.parsed_address.is_none() {
+            self.parsed_address = self.resource_path.as_deref().and_then(ResourceAddress::parse);
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub enum TerraformResourceChange {
     Create,
     Read,
@@ -59,16 +150,45 @@ pub enum TerraformResourceChange {
     Replace,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub enum TerraformResourceStatus {
     Planned,
     Started,
     InProgress,
     Done,
     Completed,
+    Failed,
+    AcquiringLock,
+    ReleasingLock,
+    Drifted,
+    Notice,
+    // "(addr): Refreshing state...", before planning.
+    Refreshing,
+    // Last event of an async stream (`run_apply_stream`), once Terraform exited.
+    Exited,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum TerraformRemediation {
+    // Run `init` with `-upgrade` (`InitOptions::upgrade`), e.g. for an inconsistent dependency lock file.
+    InitUpgrade,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum TerraformLogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
 }
 
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+// Serialized as "stdout"/"stderr", or as 1/2 with the `numeric-source-stream` feature.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "numeric-source-stream"), derive(Deserialize, Serialize))]
+#[cfg_attr(not(feature = "numeric-source-stream"), serde(rename_all = "lowercase"))]
+#[cfg_attr(feature = "numeric-source-stream", derive(Deserialize_repr, Serialize_repr))]
 #[repr(u8)]
 pub enum TerraformSourceStream {
     Stdout = 1,
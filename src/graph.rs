@@ -0,0 +1,7 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct GraphDot {
+    pub dot: String,
+    pub dependencies: Option<HashMap<String, Vec<String>>>,
+}
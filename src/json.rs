@@ -0,0 +1,141 @@
+use crate::diagnostic::TerraformDiagnostic;
+use crate::event::{TerraformLogLevel, TerraformResourceChange};
+use crate::plan::{Plan, ResourceChange};
+use chrono::{DateTime, FixedOffset};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// A line of Terraform's machine readable UI (`-json`), limited to the fields used by the parser.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonMessage {
+    #[serde(rename = "@message")]
+    pub message: String,
+    #[serde(rename = "@timestamp")]
+    #[serde(default)]
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    #[serde(rename = "@level")]
+    #[serde(default)]
+    pub level: Option<TerraformLogLevel>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub changes: Option<JsonChangeSummary>,
+    #[serde(default)]
+    pub change: Option<JsonPlannedChange>,
+    #[serde(default)]
+    pub hook: Option<JsonHook>,
+    #[serde(default)]
+    pub outputs: Option<HashMap<String, JsonOutput>>,
+    #[serde(default)]
+    pub diagnostic: Option<TerraformDiagnostic>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonChangeSummary {
+    pub add: u32,
+    pub change: u32,
+    pub remove: u32,
+    #[serde(default)]
+    pub import: u32,
+}
+
+// `terraform version -json`
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonVersion {
+    pub terraform_version: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonResource {
+    pub addr: String,
+}
+
+// Resource progress of the "apply_start", "apply_progress", "apply_complete" and "apply_errored" messages.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonHook {
+    pub resource: JsonResource,
+    pub action: String,
+    #[serde(default)]
+    pub id_key: Option<String>,
+    #[serde(default)]
+    pub id_value: Option<String>,
+    #[serde(default)]
+    pub elapsed_seconds: Option<u64>,
+}
+
+// An output of the "outputs" messages: planned changes carry an `action` and no value, applied outputs carry their
+// value unless sensitive.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonOutput {
+    #[serde(default)]
+    pub sensitive: bool,
+    #[serde(default)]
+    pub value: Option<Value>,
+    #[serde(default)]
+    pub action: Option<String>,
+}
+
+// Resource change of the "planned_change" messages of plan.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonPlannedChange {
+    pub resource: JsonResource,
+    pub action: String,
+}
+
+// The parts of `terraform show -json` used to build a `Plan`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonPlan {
+    #[serde(default)]
+    pub resource_changes: Vec<JsonResourceChange>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonResourceChange {
+    pub address: String,
+    pub change: JsonChange,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct JsonChange {
+    pub actions: Vec<String>,
+    #[serde(default)]
+    pub before: Option<Value>,
+    #[serde(default)]
+    pub after: Option<Value>,
+}
+
+impl From<JsonPlan> for Plan {
+    fn from(plan: JsonPlan) -> Plan {
+        Plan {
+            resource_changes: plan.resource_changes.into_iter().map(ResourceChange::from).collect(),
+        }
+    }
+}
+
+impl From<JsonResourceChange> for ResourceChange {
+    fn from(resource_change: JsonResourceChange) -> ResourceChange {
+        let actions = &resource_change.change.actions;
+        let actions = if actions.iter().any(|a| a == "delete") && actions.iter().any(|a| a == "create") {
+            vec![TerraformResourceChange::Replace]
+        } else {
+            actions
+                .iter()
+                .filter_map(|action| match action.as_str() {
+                    "create" => Some(TerraformResourceChange::Create),
+                    "read" => Some(TerraformResourceChange::Read),
+                    "update" => Some(TerraformResourceChange::Update),
+                    "delete" => Some(TerraformResourceChange::Destroy),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        ResourceChange {
+            address: resource_change.address,
+            actions,
+            before: resource_change.change.before.filter(|value| !value.is_null()),
+            after: resource_change.change.after.filter(|value| !value.is_null()),
+        }
+    }
+}
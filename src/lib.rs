@@ -1,344 +1,1754 @@
-mod process;
+mod address;
+mod diagnostic;
 mod errors;
+mod estimator;
 mod event;
+mod graph;
+mod json;
+mod options;
+mod outcome;
+mod parser;
+mod plan;
+mod pool;
+mod process;
+mod sink;
+#[cfg(feature = "tokio")]
+mod stream;
+mod test_report;
+mod timing;
+mod tracker;
+mod var_file;
+mod version;
 
-use regex::Regex;
+use json::JsonVersion;
+use parser::Parser;
 use std::collections::HashMap;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ChildStdin;
 use std::sync::mpsc::Sender;
 use std::time::Duration;
+use var_file::{prepare_backend_config, VarFile};
 
-pub use process::{Process, ProcessContext};
+pub use process::{
+    CommandConfigurator, Error as ProcessError, MetricsCallback, Process, ProcessContext, ProcessRegistry,
+};
 
+#[cfg(feature = "ctrlc")]
+pub use process::install_interrupt_handler;
+
+pub use address::{ResourceAddress, ResourceIndexKey};
+pub use diagnostic::{
+    TerraformDiagnostic, TerraformDiagnosticPosition, TerraformDiagnosticRange, TerraformDiagnosticSeverity,
+};
 pub use errors::Error;
-pub use event::{TerraformEvent, TerraformResourceChange, TerraformResourceStatus, TerraformSourceStream};
+pub use estimator::ApplyEstimator;
+pub use event::{
+    TerraformEvent, TerraformLogLevel, TerraformRemediation, TerraformResourceChange, TerraformResourceStatus,
+    TerraformSourceStream,
+};
+pub use graph::GraphDot;
+pub use options::{ApplyRequest, CommonFlags, DestroyConfirm, InitOptions, PlanRequest};
+pub use outcome::{ExitCodePolicy, RunOutcome};
+pub use parser::LineParser;
+pub use plan::{Plan, PlanFile, PlanFileInfo, PlanSummary, ResourceChange};
+pub use pool::TerraformPool;
+pub use sink::EventSink;
+pub use test_report::TestReport;
+pub use timing::TimingReport;
+pub use tracker::ResourceTracker;
+
+pub type InputProvider = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+pub type ApprovalCallback = Box<dyn Fn(&str) -> bool + Send + Sync>;
 
-pub struct Terraform<P, Q>
+// Upper bound of the doubling retry backoff, unless the initial backoff is already longer.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(300);
+
+// What an apply changes.
+#[derive(Clone, Copy)]
+enum ApplyMode<'a> {
+    // The changes of a saved plan.
+    Plan(&'a Path),
+    // The changes of the configuration, planned by the apply itself.
+    Direct,
+    // Only the state, to match the real infrastructure.
+    RefreshOnly,
+}
+
+pub struct Terraform<P, Q, S = Sender<TerraformEvent>>
 where
     P: AsRef<Path>,
     Q: AsRef<Path>,
+    S: EventSink,
 {
     pub process: Process<P, Q>,
-    pub sender: Sender<TerraformEvent>,
-    plan_change_regex: Regex,
-    pre_apply_regex: Regex,
-    still_applying_regex: Regex,
-    post_apply_regex: Regex,
-    plan_completed_regex: Regex,
-    apply_completed_regex: Regex,
-    destroy_completed_regex: Regex,
+    pub sender: S,
+    input_provider: Option<InputProvider>,
+    auto_approve: bool,
+    approval_callback: Option<ApprovalCallback>,
+    destroy_confirmation: DestroyConfirm,
+    confirmation_provider: Option<InputProvider>,
+    line_parser: Option<Box<dyn LineParser + Send + Sync>>,
+    create_plan_directory: bool,
+    verify_plan: bool,
+    remove_plan_on_failure: bool,
+    flags: CommonFlags,
+    quiet: bool,
+    summary_only: bool,
+    read_only: bool,
+    json_output: bool,
+    state_path: Option<PathBuf>,
+    state_out_path: Option<PathBuf>,
+    stack: Option<String>,
+    default_args: HashMap<String, Vec<String>>,
+    exit_code_policy: ExitCodePolicy,
+    parser: Parser,
 }
 
-impl<P, Q> Terraform<P, Q>
+impl<P, Q, S> Terraform<P, Q, S>
 where
     P: AsRef<Path>,
     Q: AsRef<Path>,
+    S: EventSink,
 {
     pub fn new(
         binary_path: P,
         working_directory: Q,
         envs: HashMap<String, String>,
         timeout: Duration,
-        sender: Sender<TerraformEvent>,
+        sender: S,
     ) -> Result<Self, Error> {
         let process = Process::new(binary_path, working_directory, envs, timeout);
 
         Ok(Self {
             process,
             sender,
-            // "  # %s will be created"
-            // "  # %s will be read during apply"
-            // "  # %s will be updated in-place"
-            // "  # %s will be destroyed"
-            // "  # %s is tainted, so must be replaced"
-            // "  # %s must be replaced"
-            plan_change_regex: Regex::new(
-                "  # (?P<address>.+) ((will be ((?P<action_create>created)|((?P<action_read>read) during apply)|((?P<action_update>updated) in-place)|(?P<action_destroy>destroyed)))|((is tainted, so )?must be (?P<action_replace>replaced)))"
-            )?,
-            // "(addr)( \(generation\))?: (Destroying|Creating|Modifying|Reading)...( [key=value])?"
-            pre_apply_regex: Regex::new(
-                r"^(?P<address>.+)( \((?P<generation>.*)\))?: (?P<action>(Destroying|Creating|Modifying|Reading))\.\.\.(?: \[(?P<id_key>.+)=(?P<id_value>.+)\])?$",
-            )?,
-            // "(addr): Still (modifying|destroying|creating|reading)... [(key=value, )?(elapsed)]"
-            still_applying_regex: Regex::new(
-                r"^(?P<address>.+): Still (?P<action>(modifying|destroying|creating|reading))\.\.\. \[(?:(?P<id_key>.+)=(?P<id_value>.+), )?(?P<elapsed>\d+\w+) elapsed\]",
-            )?,
-            // "(addr): (Modifications|Destruction|Creation|Read) complete after (elapsed)( [key=value])?"
-            post_apply_regex: Regex::new(
-                r"^(?P<address>.+): (?P<action>(Modifications|Destruction|Creation|Read)) complete after (?P<elapsed>\d+\w+)(?: \[(?P<id_key>.+)=(?P<id_value>.+)\])?$",
-            )?,
-
-            plan_completed_regex: Regex::new(r"Plan: (?P<add_count>\d)+ to add, (?P<change_count>\d)+ to change, (?P<destroy_count>\d)+ to destroy.")?,
-            apply_completed_regex: Regex::new(r"Apply complete! Resources: (?P<add_count>\d)+ added, (?P<change_count>\d)+ changed, (?P<destroy_count>\d)+ destroyed.")?,
-            destroy_completed_regex: Regex::new(r"Destroy complete! Resources: (?P<destroy_count>\d)+ destroyed.")?,
+            input_provider: None,
+            auto_approve: true,
+            approval_callback: None,
+            destroy_confirmation: DestroyConfirm::default(),
+            confirmation_provider: None,
+            line_parser: None,
+            create_plan_directory: false,
+            verify_plan: false,
+            remove_plan_on_failure: false,
+            flags: CommonFlags::default(),
+            quiet: false,
+            summary_only: false,
+            read_only: false,
+            json_output: false,
+            state_path: None,
+            state_out_path: None,
+            stack: None,
+            default_args: HashMap::new(),
+            exit_code_policy: ExitCodePolicy::default(),
+            parser: Parser::new()?,
         })
     }
 
-    pub fn run_init(&self) -> Result<ProcessContext, Error> {
-        let command: &str = "init";
+    // The provider receives the name of each variable Terraform prompts for, and answers it by returning a value.
+    // Returning `None` closes the input stream, which makes Terraform fail instead of waiting forever.
+    pub fn set_input_provider(&mut self, input_provider: Option<InputProvider>) {
+        self.input_provider = input_provider;
+    }
 
-        Ok(self.process.spawn(vec!["init", "-force-copy", "-no-color"])?.wait(
-            |stdout| {
-                if let Some(stdout) = stdout {
-                    let _ = self.sender.send(TerraformEvent {
-                        command: String::from(command),
-                        source: stdout,
-                        source_stream: TerraformSourceStream::Stdout,
-                        ..TerraformEvent::default()
-                    });
-                }
-            },
-            |stderr| {
-                if let Some(stderr) = stderr {
-                    let _ = self.sender.send(TerraformEvent {
-                        command: String::from(command),
-                        source: stderr,
-                        source_stream: TerraformSourceStream::Stderr,
-                        ..TerraformEvent::default()
-                    });
-                }
-            },
-        )?)
+    // When disabled, apply and destroy only go ahead once the approval callback returned true, and fail with
+    // `Error::ApprovalDenied` otherwise (or when there is no callback). Terraform requires `-auto-approve` along
+    // with `-json` unless applying a saved plan, so the other applies and destroy fail with
+    // `Error::ApprovalUnsupported` when combined with JSON output.
+    pub fn set_auto_approve(&mut self, auto_approve: bool) {
+        self.auto_approve = auto_approve;
     }
 
-    pub fn run_plan(&self, target_plan: P) -> Result<ProcessContext, Error>
+    // The callback receives the plan as displayed by Terraform: `terraform show` of the plan file for apply
+    // (saved plans are applied without prompting), and the output preceding the confirmation prompt for destroy and
+    // refresh-only apply.
+    pub fn set_approval_callback(&mut self, approval_callback: Option<ApprovalCallback>) {
+        self.approval_callback = approval_callback;
+    }
+
+    pub fn set_destroy_confirmation(&mut self, destroy_confirmation: DestroyConfirm) {
+        self.destroy_confirmation = destroy_confirmation;
+    }
+
+    // Answers `DestroyConfirm::RequirePhrase`: the provider receives the output preceding the destroy confirmation
+    // prompt (the plan), and returns the phrase typed by the user.
+    pub fn set_confirmation_provider(&mut self, confirmation_provider: Option<InputProvider>) {
+        self.confirmation_provider = confirmation_provider;
+    }
+
+    // Consulted before the built-in parsing for every stdout and stderr line.
+    pub fn set_line_parser(&mut self, line_parser: Option<Box<dyn LineParser + Send + Sync>>) {
+        self.line_parser = line_parser;
+    }
+
+    pub fn set_refresh(&mut self, refresh: bool) {
+        self.flags.refresh = refresh;
+    }
+
+    pub fn set_common_flags(&mut self, flags: CommonFlags) {
+        self.flags = flags;
+    }
+
+    pub fn common_flags(&self) -> &CommonFlags {
+        &self.flags
+    }
+
+    // Passes `-compact-warnings` to plan, apply and destroy, and doesn't emit events for blank, separator and
+    // refresh progress lines (they are still captured in the returned context).
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    // For large plans: plan only sends its `Completed` event (with the counts) for stdout, instead of an event per
+    // line. Stderr events are still sent, and the output is still captured in the returned context.
+    pub fn set_summary_only(&mut self, summary_only: bool) {
+        self.summary_only = summary_only;
+    }
+
+    // Makes apply, destroy, state changes and workspace deletion fail with `Error::OperationForbidden` before anything
+    // is spawned.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    // Passes `-json` to plan, apply and destroy: events carry each message's "@message" text, "planned_change"
+    // messages and the resource progress of apply are reported as the matching human readable lines would be, the
+    // "change_summary" message sets the counts of the `Completed` event, and the applied "outputs" the outputs.
+    pub fn set_json_output(&mut self, json_output: bool) {
+        self.json_output = json_output;
+    }
+
+    // Legacy local backend state file, passed as `-state` to plan, apply and destroy.
+    pub fn set_state_path(&mut self, state_path: Option<PathBuf>) {
+        self.state_path = state_path;
+    }
+
+    // Passed as `-state-out` to apply and destroy.
+    pub fn set_state_out_path(&mut self, state_out_path: Option<PathBuf>) {
+        self.state_out_path = state_out_path;
+    }
+
+    // Selects the workspace of every command with `TF_WORKSPACE`, instead of `terraform workspace select`. Names
+    // must be left unchanged by URL path segment escaping, as Terraform requires (Go's `url.PathEscape` escapes "," and
+    // ";" too).
+    pub fn with_workspace(&mut self, name: &str) -> Result<(), Error> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-._~$&+=:@".contains(c));
+        if !valid {
+            return Err(Error::InvalidWorkspaceName(String::from(name)));
+        }
+
+        self.process.set_env("TF_WORKSPACE", name);
+        Ok(())
+    }
+
+    // Tags every event sent by this instance, see `TerraformPool`.
+    pub fn set_stack(&mut self, stack: Option<String>) {
+        self.stack = stack;
+    }
+
+    // Arguments inserted right after the subcommand they are registered for, like Terraform's `TF_CLI_ARGS_name`.
+    // Nested subcommands are named with their words joined by spaces ("state mv", "workspace delete").
+    pub fn set_default_args(&mut self, subcommand: &str, args: Vec<String>) {
+        self.default_args.insert(String::from(subcommand), args);
+    }
+
+    // Decides the `outcome` of every returned context.
+    pub fn set_exit_code_policy(&mut self, exit_code_policy: ExitCodePolicy) {
+        self.exit_code_policy = exit_code_policy;
+    }
+
+    pub fn set_create_plan_directory(&mut self, create_plan_directory: bool) {
+        self.create_plan_directory = create_plan_directory;
+    }
+
+    // Once a plan succeeded, reads the plan file back with `terraform show`, failing with `Error::PlanCorrupt` when it
+    // can't be read (e.g. truncated on a full disk) instead of letting the apply fail later.
+    pub fn set_verify_plan(&mut self, verify_plan: bool) {
+        self.verify_plan = verify_plan;
+    }
+
+    // Removes the plan file when its apply fails, times out or is interrupted, so a stale plan can't be applied later
+    // by mistake. The removal is reported with a `Notice` event.
+    pub fn set_remove_plan_on_failure(&mut self, remove_plan_on_failure: bool) {
+        self.remove_plan_on_failure = remove_plan_on_failure;
+    }
+
+    // The returned guard removes the plan file (relative to the working directory) when dropped.
+    pub fn plan_file(&self, target_plan: P) -> PlanFile
     where
         P: AsRef<Path>,
     {
-        let command: &str = "plan";
-        let plan_path = target_plan.as_ref().to_str().ok_or(Error::PathError)?;
-        let out_arg = format!("-out={}", plan_path);
+        PlanFile::new(self.process.working_directory().join(target_plan))
+    }
 
-        Ok(self.process.spawn(vec!["plan", "-input=false", out_arg.as_ref(), "-no-color"])?.wait(
-            |stdout| {
-                if let Some(stdout) = stdout {
-                    let _ = self.sender.send(TerraformEvent {
-                        command: String::from(command),
-                        ..self.parse_plan_stdout(stdout)
-                    });
-                }
-            },
-            |stderr| {
-                if let Some(stderr) = stderr {
-                    let _ = self.sender.send(TerraformEvent {
-                        command: String::from(command),
-                        source: stderr,
-                        source_stream: TerraformSourceStream::Stderr,
-                        ..TerraformEvent::default()
-                    });
-                }
-            },
-        )?)
+    pub fn run_init(&self) -> Result<ProcessContext, Error> {
+        self.run_init_with_envs(&HashMap::new())
     }
 
-    pub fn run_apply(&self, target_plan: P) -> Result<ProcessContext, Error>
+    pub fn run_init_with_envs(&self, envs: &HashMap<String, String>) -> Result<ProcessContext, Error> {
+        self.init(&InitOptions::default(), envs, &[])
+    }
+
+    pub fn run_init_with_options(&self, options: &InitOptions) -> Result<ProcessContext, Error> {
+        self.init(options, &HashMap::new(), &[])
+    }
+
+    // `extra_args` are passed as is, for flags without a dedicated option.
+    pub fn run_init_with_args(&self, extra_args: &[String]) -> Result<ProcessContext, Error> {
+        self.init(&InitOptions::default(), &HashMap::new(), extra_args)
+    }
+
+    fn init(
+        &self,
+        options: &InitOptions,
+        envs: &HashMap<String, String>,
+        extra_args: &[String],
+    ) -> Result<ProcessContext, Error> {
+        let mut envs = envs.clone();
+        if let Some(plugin_cache_dir) = &options.plugin_cache_dir {
+            let plugin_cache_dir = self.process.working_directory().join(plugin_cache_dir);
+            std::fs::create_dir_all(&plugin_cache_dir)?;
+
+            envs.insert(
+                String::from("TF_PLUGIN_CACHE_DIR"),
+                String::from(plugin_cache_dir.to_str().ok_or(Error::PathError)?),
+            );
+        }
+
+        let backend_config = if options.backend_config.is_empty() {
+            None
+        } else {
+            Some(prepare_backend_config(&options.backend_config)?)
+        };
+        let backend_config_arg = match &backend_config {
+            Some(file) => Some(format!(
+                "-backend-config={}",
+                file.path().to_str().ok_or(Error::PathError)?
+            )),
+            None => None,
+        };
+
+        let common_args = self.common_args("init", self.flags.input);
+
+        let mut args = vec!["init"];
+        args.extend(common_args.iter().map(String::as_str));
+        if !options.backend {
+            args.push("-backend=false");
+        }
+        if options.migrate_state {
+            args.push("-migrate-state");
+        }
+        if options.force_copy {
+            args.push("-force-copy");
+        }
+        if options.upgrade {
+            args.push("-upgrade");
+        }
+        args.extend(backend_config_arg.as_deref());
+        args.extend(extra_args.iter().map(String::as_str));
+
+        self.run_parsed_command(
+            "init",
+            args,
+            &envs,
+            |stdout| self.parser.parse_init_stdout(stdout),
+            |stderr| self.parser.parse_init_stderr(stderr),
+        )
+    }
+
+    pub fn run_init_with_retry(&self, attempts: u32, backoff: Duration) -> Result<ProcessContext, Error> {
+        self.with_retry(attempts, backoff, || self.run_init())
+    }
+
+    pub fn run_test(&self) -> Result<TestReport, Error> {
+        let context = self.run_command("test", vec!["test", "-no-color"])?;
+
+        Ok(self.parser.parse_test_report(&context))
+    }
+
+    pub fn run_graph(&self) -> Result<GraphDot, Error> {
+        let context = self.capture_command("graph", vec!["graph"])?;
+        if context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(context.exit_code));
+        }
+        let dot = context.stdout_string();
+
+        Ok(GraphDot {
+            dependencies: self.parser.parse_graph_dependencies(&dot),
+            dot,
+        })
+    }
+
+    pub fn providers_lock(&self, platforms: &[String]) -> Result<ProcessContext, Error> {
+        let platform_args: Vec<String> = platforms
+            .iter()
+            .map(|platform| format!("-platform={}", platform))
+            .collect();
+
+        let mut args = vec!["providers", "lock", "-no-color"];
+        args.extend(platform_args.iter().map(String::as_str));
+
+        self.run_command("providers", args)
+    }
+
+    pub fn providers_mirror(&self, dir: P) -> Result<ProcessContext, Error>
     where
         P: AsRef<Path>,
     {
-        let command: &str = "apply";
-        let plan_path = target_plan.as_ref().to_str().ok_or(Error::PathError)?;
+        let dir_path = dir.as_ref().to_str().ok_or(Error::PathError)?;
 
-        Ok(self
-            .process
-            .spawn(vec!["apply", "-auto-approve", "-input=false", "-no-color", plan_path])?
-            .wait(
-                |stdout| {
-                    if let Some(stdout) = stdout {
-                        let _ = self.sender.send(TerraformEvent {
-                            command: String::from(command),
-                            ..self.parse_apply_stdout(stdout)
-                        });
-                    }
-                },
-                |stderr| {
-                    if let Some(stderr) = stderr {
-                        let _ = self.sender.send(TerraformEvent {
-                            command: String::from(command),
-                            source: stderr,
-                            source_stream: TerraformSourceStream::Stderr,
-                            ..TerraformEvent::default()
-                        });
-                    }
-                },
-            )?)
+        self.run_command("providers", vec!["providers", "mirror", "-no-color", dir_path])
     }
 
-    pub fn run_destroy(&self) -> Result<ProcessContext, Error> {
-        let command: &str = "destroy";
+    // Preflight check: fails with `Error::NotAConfiguration` when the working directory doesn't contain any ".tf" or
+    // ".tf.json" file, and with `Error::BinaryUnavailable` when `terraform version` can't be run.
+    pub fn check(&self) -> Result<(), Error> {
+        let working_directory = self.process.working_directory();
+        let not_a_configuration = || Error::NotAConfiguration(working_directory.to_path_buf());
 
-        Ok(self
-            .process
-            .spawn(vec!["destroy", "-auto-approve", "-no-color"])?
-            .wait(
-                |stdout| {
-                    if let Some(stdout) = stdout {
-                        let _ = self.sender.send(TerraformEvent {
-                            command: String::from(command),
-                            ..self.parse_apply_stdout(stdout)
-                        });
-                    }
-                },
-                |stderr| {
-                    if let Some(stderr) = stderr {
-                        let _ = self.sender.send(TerraformEvent {
-                            command: String::from(command),
-                            source: stderr,
-                            source_stream: TerraformSourceStream::Stderr,
-                            ..TerraformEvent::default()
-                        });
-                    }
-                },
-            )?)
+        let mut has_configuration = false;
+        for entry in std::fs::read_dir(working_directory).map_err(|_| not_a_configuration())? {
+            let name = entry?.file_name();
+            let name = name.to_string_lossy();
+            has_configuration |= name.ends_with(".tf") || name.ends_with(".tf.json");
+        }
+        if !has_configuration {
+            return Err(not_a_configuration());
+        }
+
+        let context = self.capture_command("version", vec!["version"]).map_err(|e| match e {
+            Error::ProcessError(ProcessError::IOError(_, message)) => {
+                Error::BinaryUnavailable(self.process.binary_path().to_path_buf(), message)
+            }
+            e => e,
+        })?;
+        if context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(context.exit_code));
+        }
+
+        Ok(())
+    }
+
+    // Downloads or updates the modules of the configuration, with an event per module.
+    pub fn run_get(&self) -> Result<ProcessContext, Error> {
+        self.run_parsed_command(
+            "get",
+            vec!["get", "-no-color"],
+            &HashMap::new(),
+            |stdout| self.parser.parse_get_stdout(stdout),
+            |stderr| self.parser.parse_stderr(stderr),
+        )
     }
 
-    fn parse_plan_stdout(&self, stdout: String) -> TerraformEvent {
-        if let Some(captures) = self.plan_change_regex.clone().captures(stdout.as_str()) {
-            let (address, _, _) = self.parse_context_captures(&captures);
+    // The installed Terraform version, e.g. "1.5.7".
+    pub fn version(&self) -> Result<String, Error> {
+        let context = self.capture_command("version", vec!["version", "-json"])?;
+        if context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(context.exit_code));
+        }
 
-            TerraformEvent {
-                change: self.captures_to_change(captures),
-                status: Some(TerraformResourceStatus::Planned),
-                resource_path: address,
-                source: stdout,
-                ..TerraformEvent::default()
-            }
-        } else if let Some(captures) = self.plan_completed_regex.clone().captures(stdout.as_str()) {
-            let (create_count, update_count, delete_count) = self.parse_stats_captures(&captures);
+        Ok(serde_json::from_str::<JsonVersion>(&context.stdout_string())?.terraform_version)
+    }
 
-            TerraformEvent {
-                status: Some(TerraformResourceStatus::Completed),
-                source: stdout,
-                create_count,
-                update_count,
-                delete_count,
-                ..TerraformEvent::default()
-            }
-        } else {
-            TerraformEvent {
-                status: Some(TerraformResourceStatus::Planned),
-                source: stdout,
-                ..TerraformEvent::default()
+    // The `required_version` constraints of the ".tf" files of the working directory (modules aren't scanned), joined
+    // with ", " when several `terraform {}` blocks have one. This is a line based scan, not an HCL parser.
+    pub fn required_version(&self) -> Result<Option<String>, Error> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(self.process.working_directory())? {
+            let path = entry?.path();
+            if path.extension().map(|extension| extension == "tf").unwrap_or(false) {
+                paths.push(path);
             }
         }
+        paths.sort();
+
+        let mut constraints = Vec::new();
+        for path in paths {
+            let content = std::fs::read_to_string(path)?;
+            constraints.extend(
+                self.parser
+                    .required_version_regex
+                    .captures_iter(&content)
+                    .filter_map(|captures| captures.name("constraint"))
+                    .map(|m| String::from(m.as_str())),
+            );
+        }
+
+        Ok(Some(constraints.join(", ")).filter(|constraints| !constraints.is_empty()))
     }
 
-    fn parse_apply_stdout(&self, stdout: String) -> TerraformEvent {
-        if let Some(captures) = self.pre_apply_regex.clone().captures(stdout.as_str()) {
-            let (address, id_key, id_value) = self.parse_context_captures(&captures);
+    // The providers pinned by ".terraform.lock.hcl" in the working directory, from their source address (e.g.
+    // "registry.terraform.io/hashicorp/aws") to their version. Empty when there is no lock file yet.
+    pub fn locked_providers(&self) -> Result<HashMap<String, String>, Error> {
+        let path = self.process.working_directory().join(".terraform.lock.hcl");
+        let content = match std::fs::read_to_string(path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            content => content?,
+        };
 
-            TerraformEvent {
-                change: self.captures_to_change(captures),
-                status: Some(TerraformResourceStatus::Started),
-                resource_path: address,
-                id_key,
-                id_value,
-                source: stdout,
-                ..TerraformEvent::default()
-            }
-        } else if let Some(captures) = self.still_applying_regex.clone().captures(stdout.as_str()) {
-            let (address, id_key, id_value) = self.parse_context_captures(&captures);
-
-            TerraformEvent {
-                change: self.captures_to_change(captures),
-                status: Some(TerraformResourceStatus::InProgress),
-                resource_path: address,
-                id_key,
-                id_value,
-                source: stdout,
-                ..TerraformEvent::default()
-            }
-        } else if let Some(captures) = self.post_apply_regex.clone().captures(stdout.as_str()) {
-            let (address, id_key, id_value) = self.parse_context_captures(&captures);
-
-            TerraformEvent {
-                change: self.captures_to_change(captures),
-                status: Some(TerraformResourceStatus::Done),
-                resource_path: address,
-                id_key,
-                id_value,
-                source: stdout,
-                ..TerraformEvent::default()
-            }
-        } else if let Some(captures) = self.apply_completed_regex.clone().captures(stdout.as_str()) {
-            let (create_count, update_count, delete_count) = self.parse_stats_captures(&captures);
+        Ok(self.parser.parse_lock_file(&content))
+    }
 
-            TerraformEvent {
-                status: Some(TerraformResourceStatus::Completed),
-                source: stdout,
-                create_count,
-                update_count,
-                delete_count,
-                ..TerraformEvent::default()
-            }
-        } else if let Some(captures) = self.destroy_completed_regex.clone().captures(stdout.as_str()) {
-            let (create_count, update_count, delete_count) = self.parse_stats_captures(&captures);
+    // Fails with `Error::UnsupportedVersion` when the installed version doesn't satisfy `required_version`.
+    pub fn check_required_version(&self) -> Result<(), Error> {
+        let constraint = match self.required_version()? {
+            Some(constraint) => constraint,
+            None => return Ok(()),
+        };
 
-            TerraformEvent {
-                status: Some(TerraformResourceStatus::Completed),
-                source: stdout,
-                create_count,
-                update_count,
-                delete_count,
-                ..TerraformEvent::default()
-            }
-        } else {
-            TerraformEvent {
-                source: stdout,
-                ..TerraformEvent::default()
-            }
+        let installed = self.version()?;
+        let satisfied = version::Version::parse(&installed)
+            .and_then(|version| version::satisfies(version, &constraint))
+            .unwrap_or(false);
+
+        if !satisfied {
+            return Err(Error::UnsupportedVersion(installed, constraint));
+        }
+
+        Ok(())
+    }
+
+    pub fn workspace_show(&self) -> Result<String, Error> {
+        let context = self.capture_command("workspace", vec!["workspace", "show"])?;
+        if context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(context.exit_code));
+        }
+
+        Ok(String::from(context.stdout_string().trim()))
+    }
+
+    pub fn workspace_delete(&self, name: &str, force: bool) -> Result<ProcessContext, Error> {
+        if self.read_only {
+            return Err(Error::OperationForbidden);
+        }
+
+        let mut args = vec!["workspace", "delete", "-no-color"];
+        if force {
+            args.push("-force");
+        }
+        args.push(name);
+
+        self.run_command("workspace", args)
+    }
+
+    pub fn providers_schema(&self) -> Result<serde_json::Value, Error> {
+        let context = self.capture_command("providers", vec!["providers", "schema", "-json"])?;
+        if context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(context.exit_code));
+        }
+
+        Ok(serde_json::from_str(&context.stdout_string())?)
+    }
+
+    // `terraform console` has no JSON output, so the expression is wrapped in `jsonencode` and the printed string decoded.
+    pub fn console_eval(&self, expression: &str) -> Result<serde_json::Value, Error> {
+        let state_args = self.state_args(false)?;
+
+        let mut args = vec!["console", "-no-color"];
+        args.extend(state_args.iter().map(String::as_str));
+
+        let input = format!("jsonencode({})\n", expression);
+        let context = self.capture_command_with_input("console", args, Some(input.as_str()))?;
+        if context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(context.exit_code));
         }
+
+        let encoded: String = serde_json::from_str(context.stdout_string().trim())?;
+
+        Ok(serde_json::from_str(&encoded)?)
     }
 
-    fn parse_stats_captures(&self, captures: &regex::Captures) -> (Option<u32>, Option<u32>, Option<u32>) {
-        (
-            captures
-                .name("add_count")
-                .map(|m| String::from(m.as_str().trim()).parse::<u32>().ok())
-                .flatten(),
-            captures
-                .name("change_count")
-                .map(|m| String::from(m.as_str().trim()).parse::<u32>().ok())
-                .flatten(),
-            captures
-                .name("destroy_count")
-                .map(|m| String::from(m.as_str().trim()).parse::<u32>().ok())
-                .flatten(),
+    pub fn state_mv(&self, src: &str, dst: &str) -> Result<ProcessContext, Error> {
+        if self.read_only {
+            return Err(Error::OperationForbidden);
+        }
+
+        let state_args = self.state_args(true)?;
+
+        let mut args = vec!["state", "mv", "-no-color"];
+        args.extend(state_args.iter().map(String::as_str));
+        args.extend([src, dst]);
+
+        self.run_parsed_command(
+            "state",
+            args,
+            &HashMap::new(),
+            |stdout| self.parser.parse_state_stdout(stdout),
+            |stderr| self.parser.parse_stderr(stderr),
         )
     }
 
-    fn parse_context_captures(&self, captures: &regex::Captures) -> (Option<String>, Option<String>, Option<String>) {
-        (
-            captures.name("address").map(|m| String::from(m.as_str().trim())),
-            captures.name("id_key").map(|m| String::from(m.as_str().trim())),
-            captures.name("id_value").map(|m| String::from(m.as_str().trim())),
+    pub fn state_rm(&self, address: &str) -> Result<ProcessContext, Error> {
+        if self.read_only {
+            return Err(Error::OperationForbidden);
+        }
+
+        let state_args = self.state_args(false)?;
+
+        let mut args = vec!["state", "rm", "-no-color"];
+        args.extend(state_args.iter().map(String::as_str));
+        args.push(address);
+
+        self.run_parsed_command(
+            "state",
+            args,
+            &HashMap::new(),
+            |stdout| self.parser.parse_state_stdout(stdout),
+            |stderr| self.parser.parse_stderr(stderr),
         )
     }
 
-    fn captures_to_change(&self, captures: regex::Captures) -> Vec<TerraformResourceChange> {
-        if let Some(change) = captures.name("action") {
-            self.action_to_change(change.as_str())
-        } else if captures.name("action_create").is_some() {
-            vec![TerraformResourceChange::Create]
-        } else if captures.name("action_read").is_some() {
-            vec![TerraformResourceChange::Read]
-        } else if captures.name("action_update").is_some() {
-            vec![TerraformResourceChange::Update]
-        } else if captures.name("action_destroy").is_some() {
-            vec![TerraformResourceChange::Destroy]
-        } else if captures.name("action_replace").is_some() {
-            vec![TerraformResourceChange::Destroy, TerraformResourceChange::Create]
-        } else {
-            Vec::new()
+    // `input` is the effective `-input` value, which depends on the command options.
+    fn common_args(&self, command: &str, input: bool) -> Vec<String> {
+        self.common_args_with_flags(command, input, &self.flags)
+    }
+
+    fn common_args_with_flags(&self, command: &str, input: bool, flags: &CommonFlags) -> Vec<String> {
+        let mut args = Vec::new();
+        if flags.no_color {
+            args.push(String::from("-no-color"));
+        }
+        args.push(format!("-input={}", input));
+        if !flags.lock {
+            args.push(String::from("-lock=false"));
+        }
+        if let Some(lock_timeout) = flags.lock_timeout {
+            args.push(format!("-lock-timeout={}s", lock_timeout.as_secs()));
+        }
+        if command == "init" {
+            return args;
+        }
+
+        if let Some(parallelism) = flags.parallelism {
+            args.push(format!("-parallelism={}", parallelism));
+        }
+        if !flags.refresh {
+            args.push(String::from("-refresh=false"));
+        }
+        args
+    }
+
+    fn state_args(&self, with_state_out: bool) -> Result<Vec<String>, Error> {
+        let mut args = Vec::new();
+
+        if let Some(state_path) = &self.state_path {
+            args.push(format!("-state={}", state_path.to_str().ok_or(Error::PathError)?));
+        }
+
+        if let Some(state_out_path) = self.state_out_path.as_ref().filter(|_| with_state_out) {
+            args.push(format!(
+                "-state-out={}",
+                state_out_path.to_str().ok_or(Error::PathError)?
+            ));
+        }
+
+        Ok(args)
+    }
+
+    // Re-runs the command while it fails with a transient error (network, rate limiting), doubling the backoff each
+    // time up to `MAX_RETRY_BACKOFF`.
+    fn with_retry<F>(&self, attempts: u32, backoff: Duration, run: F) -> Result<ProcessContext, Error>
+    where
+        F: Fn() -> Result<ProcessContext, Error>,
+    {
+        let mut delay = backoff;
+        let mut attempt = 1;
+
+        loop {
+            let context = run()?;
+            let transient = context
+                .stderr
+                .iter()
+                .any(|line| self.parser.transient_error_regex.is_match(line));
+
+            if context.exit_code == Some(0) || attempt >= attempts || !transient {
+                return Ok(context);
+            }
+
+            std::thread::sleep(delay);
+            delay = next_backoff(delay, backoff);
+            attempt += 1;
+        }
+    }
+
+    // Lists the files that are not properly formatted, `terraform fmt` exits with 3 in that case.
+    pub fn fmt_check_recursive(&self, dir: P) -> Result<Vec<PathBuf>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let dir_path = dir.as_ref().to_str().ok_or(Error::PathError)?;
+        let context = self.capture_command("fmt", vec!["fmt", "-check", "-recursive", "-no-color", dir_path])?;
+
+        match context.exit_code {
+            Some(0) | Some(3) => Ok(context
+                .stdout
+                .iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .collect()),
+            exit_code => Err(Error::CommandFailed(exit_code)),
+        }
+    }
+
+    // The value of a single root module output, from the current state. Sensitive values are returned as well.
+    pub fn output_value(&self, name: &str) -> Result<serde_json::Value, Error> {
+        let context = self.capture_command("output", vec!["output", "-no-color", "-json", name])?;
+        if context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(context.exit_code));
         }
+
+        Ok(serde_json::from_str(&context.stdout_string())?)
     }
 
-    fn action_to_change(&self, action: &str) -> Vec<TerraformResourceChange> {
-        match action.trim() {
-            "Creating" | "creating" | "Creation" => vec![TerraformResourceChange::Create],
-            "Reading" | "reading" | "Read" => vec![TerraformResourceChange::Read],
-            "Modifying" | "modifying" | "Modifications" => vec![TerraformResourceChange::Update],
-            "Destroying" | "destroying" | "Destruction" => vec![TerraformResourceChange::Destroy],
-            _ => Vec::new(),
+    pub fn parse_plan_file(&self, plan_path: P) -> Result<Plan, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let context = self.show_json(plan_path.as_ref())?;
+        if context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(context.exit_code));
         }
+
+        Plan::from_json(&context.stdout_string())
+    }
+
+    fn show_json(&self, target_plan: &Path) -> Result<ProcessContext, Error> {
+        let plan_path = target_plan.to_str().ok_or(Error::PathError)?;
+
+        self.capture_command("show", vec!["show", "-json", "-no-color", plan_path])
+    }
+
+    fn run_command(&self, command: &str, args: Vec<&str>) -> Result<ProcessContext, Error> {
+        self.run_parsed_command(
+            command,
+            args,
+            &HashMap::new(),
+            |stdout| TerraformEvent {
+                source: stdout,
+                source_stream: TerraformSourceStream::Stdout,
+                ..TerraformEvent::default()
+            },
+            |stderr| self.parser.parse_stderr(stderr),
+        )
+    }
+
+    fn run_parsed_command<O, E>(
+        &self,
+        command: &str,
+        args: Vec<&str>,
+        envs: &HashMap<String, String>,
+        mut parse_stdout: O,
+        mut parse_stderr: E,
+    ) -> Result<ProcessContext, Error>
+    where
+        O: FnMut(String) -> TerraformEvent,
+        E: FnMut(String) -> TerraformEvent,
+    {
+        let context = self.spawn(args, envs)?.wait(
+            |stdout| {
+                if let Some(stdout) = stdout {
+                    self.send_event(command, stdout, TerraformSourceStream::Stdout, |stdout| {
+                        parse_stdout(stdout)
+                    });
+                }
+            },
+            |stderr| {
+                if let Some(stderr) = stderr {
+                    self.send_event(command, stderr, TerraformSourceStream::Stderr, |stderr| {
+                        parse_stderr(stderr)
+                    });
+                }
+            },
+        )?;
+
+        Ok(self.with_outcome(command, context))
+    }
+
+    pub fn run_plan(&self, target_plan: P) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.plan(target_plan.as_ref(), &HashMap::new(), &[])
+    }
+
+    pub fn run_plan_with_envs(&self, target_plan: P, envs: &HashMap<String, String>) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.plan(target_plan.as_ref(), envs, &[])
+    }
+
+    // `extra_args` are passed as is, for flags without a dedicated option.
+    pub fn run_plan_with_args(&self, target_plan: P, extra_args: &[String]) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.plan(target_plan.as_ref(), &HashMap::new(), extra_args)
+    }
+
+    // Plans, then checks the plan file was actually written: `Error::PlanFileMissing` otherwise. A failed plan is
+    // reported as `Error::CommandFailed` instead.
+    pub fn run_plan_with_file_info(&self, target_plan: P) -> Result<(ProcessContext, PlanFileInfo), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let context = self.plan(target_plan.as_ref(), &HashMap::new(), &[])?;
+        if context.outcome == Some(RunOutcome::Failure) {
+            return Err(Error::CommandFailed(context.exit_code));
+        }
+
+        Ok((context, self.plan_file_info(target_plan)?))
+    }
+
+    // With `forbid_destroy`, fails with `Error::DestructivePlan` when the plan would destroy or replace any resource.
+    // The plan file is kept, the caller decides what to do with it.
+    pub fn run_plan_checked(&self, target_plan: P, forbid_destroy: bool) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut addresses = Vec::new();
+        let context = self.run_plan_destructive(target_plan, |event| {
+            addresses.extend(event.resource_path.clone());
+        })?;
+
+        if forbid_destroy && !addresses.is_empty() {
+            return Err(Error::DestructivePlan(addresses));
+        }
+
+        Ok(context)
+    }
+
+    pub fn run_plan_with_summary(&self, target_plan: P) -> Result<(ProcessContext, PlanSummary), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let context = self.plan(target_plan.as_ref(), &HashMap::new(), &[])?;
+        if context.outcome == Some(RunOutcome::Failure) {
+            return Err(Error::CommandFailed(context.exit_code));
+        }
+        let file = self.plan_file_info(target_plan)?;
+
+        let events = self.parser.parse_captured("plan", &context.stdout, &[]);
+        let completed = events
+            .iter()
+            .rev()
+            .find(|event| event.status == Some(TerraformResourceStatus::Completed));
+
+        let summary = PlanSummary {
+            file,
+            create_count: completed.and_then(|event| event.create_count).unwrap_or(0),
+            update_count: completed.and_then(|event| event.update_count).unwrap_or(0),
+            delete_count: completed.and_then(|event| event.delete_count).unwrap_or(0),
+            import_count: completed.and_then(|event| event.import_count).unwrap_or(0),
+            resource_changes: events
+                .iter()
+                .filter(|event| {
+                    event.status == Some(TerraformResourceStatus::Planned)
+                        && event.resource_path.is_some()
+                        && event.change != [TerraformResourceChange::Read]
+                })
+                .count(),
+        };
+
+        Ok((context, summary))
+    }
+
+    pub fn plan_file_info(&self, target_plan: P) -> Result<PlanFileInfo, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = self.process.working_directory().join(target_plan);
+        let metadata = match std::fs::metadata(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(Error::PlanFileMissing(path)),
+            metadata => metadata?,
+        };
+
+        Ok(PlanFileInfo {
+            size_bytes: metadata.len(),
+            modified: metadata.modified().ok(),
+            path,
+        })
+    }
+
+    // The variable files Terraform loads by itself from the working directory, in its loading order (later files
+    // override earlier ones): "terraform.tfvars", "terraform.tfvars.json", then "*.auto.tfvars" and
+    // "*.auto.tfvars.json" in lexical order. A saved plan already carries the values read at plan time.
+    pub fn auto_var_files(&self) -> Result<Vec<PathBuf>, Error> {
+        let working_directory = self.process.working_directory();
+
+        let mut auto_files = Vec::new();
+        for entry in std::fs::read_dir(working_directory)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if (name.ends_with(".auto.tfvars") || name.ends_with(".auto.tfvars.json")) && entry.path().is_file() {
+                auto_files.push(name);
+            }
+        }
+        auto_files.sort();
+
+        Ok(["terraform.tfvars", "terraform.tfvars.json"]
+            .iter()
+            .map(|name| working_directory.join(name))
+            .filter(|path| path.is_file())
+            .chain(auto_files.into_iter().map(|name| working_directory.join(name)))
+            .collect())
+    }
+
+    // Plans `import` blocks without a matching resource, writing their configuration to `config_out`.
+    // The generated file is reported by an event with `generated_config` set.
+    pub fn run_plan_generate_config(&self, target_plan: P, config_out: P) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let config_out_arg = format!(
+            "-generate-config-out={}",
+            config_out.as_ref().to_str().ok_or(Error::PathError)?
+        );
+
+        self.plan(target_plan.as_ref(), &HashMap::new(), &[config_out_arg])
+    }
+
+    pub fn run_plan_with_retry(&self, target_plan: P, attempts: u32, backoff: Duration) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.with_retry(attempts, backoff, || {
+            self.plan(target_plan.as_ref(), &HashMap::new(), &[])
+        })
+    }
+
+    // Plans into `target_plan`, then writes its `terraform show -json` representation to `json_path`.
+    pub fn run_plan_json(&self, target_plan: P, json_path: P) -> Result<(ProcessContext, ProcessContext), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let plan_context = self.plan(target_plan.as_ref(), &HashMap::new(), &[])?;
+        if plan_context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(plan_context.exit_code));
+        }
+
+        let show_context = self.show_json(target_plan.as_ref())?;
+        if show_context.exit_code != Some(0) {
+            return Err(Error::CommandFailed(show_context.exit_code));
+        }
+
+        std::fs::write(
+            self.process.working_directory().join(json_path),
+            show_context.stdout_string(),
+        )?;
+
+        Ok((plan_context, show_context))
+    }
+
+    // Plans with `-json` (even when JSON output is disabled), each event carrying the human readable text of its
+    // message, then reads the plan file back as a `Plan` with `terraform show -json`.
+    pub fn run_plan_dual(&self, target_plan: P) -> Result<(ProcessContext, Plan), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let extra_args: Vec<String> = if self.json_output {
+            vec![]
+        } else {
+            vec![String::from("-json")]
+        };
+
+        let context = self.plan(target_plan.as_ref(), &HashMap::new(), &extra_args)?;
+        if context.outcome == Some(RunOutcome::Failure) {
+            return Err(Error::CommandFailed(context.exit_code));
+        }
+
+        let plan = self.parse_plan_file(target_plan)?;
+
+        Ok((context, plan))
+    }
+
+    // Plans into `target_plan`, then calls `on_destructive` for each planned destroy or replacement, in plan order,
+    // to present what would be removed before applying. Every event is still sent as usual while planning.
+    pub fn run_plan_destructive<F>(&self, target_plan: P, mut on_destructive: F) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&TerraformEvent),
+    {
+        let context = self.plan(target_plan.as_ref(), &HashMap::new(), &[])?;
+
+        self.parser
+            .parse_captured("plan", &context.stdout, &[])
+            .iter()
+            .filter(|event| event.status == Some(TerraformResourceStatus::Planned) && event.is_destructive())
+            .for_each(&mut on_destructive);
+
+        Ok(context)
+    }
+
+    // A single entry point for targets, replacements, variables and flags, instead of the `run_plan_*` variants.
+    pub fn run_plan_request(&self, request: &PlanRequest) -> Result<ProcessContext, Error> {
+        self.plan_with_flags(
+            &request.out,
+            &HashMap::new(),
+            &request.args()?,
+            &request.flags(&self.flags),
+        )
+    }
+
+    fn plan(
+        &self,
+        target_plan: &Path,
+        envs: &HashMap<String, String>,
+        extra_args: &[String],
+    ) -> Result<ProcessContext, Error> {
+        self.plan_with_flags(target_plan, envs, extra_args, &self.flags)
+    }
+
+    fn plan_with_flags(
+        &self,
+        target_plan: &Path,
+        envs: &HashMap<String, String>,
+        extra_args: &[String],
+        flags: &CommonFlags,
+    ) -> Result<ProcessContext, Error> {
+        let command: &str = "plan";
+        let plan_path = target_plan.to_str().ok_or(Error::PathError)?;
+
+        if self.create_plan_directory {
+            if let Some(parent) = self.process.working_directory().join(plan_path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        let out_arg = format!("-out={}", plan_path);
+        let common_args = self.common_args_with_flags(command, flags.input || self.input_provider.is_some(), flags);
+        let state_args = self.state_args(false)?;
+
+        let mut args = vec!["plan", out_arg.as_ref()];
+        args.extend(common_args.iter().map(String::as_str));
+        if self.quiet {
+            args.push("-compact-warnings");
+        }
+        if self.json_output {
+            args.push("-json");
+        }
+        args.extend(state_args.iter().map(String::as_str));
+        args.extend(extra_args.iter().map(String::as_str));
+
+        let mut context = self.spawn(args, envs)?;
+        let mut stdin = context.take_stdin().filter(|_| self.input_provider.is_some());
+
+        let context = context.wait(
+            |stdout| {
+                if let Some(stdout) = stdout {
+                    self.answer_input_prompt(stdout.as_str(), &mut stdin);
+                    if !self.summary_only || self.parser.is_plan_summary(stdout.as_str()) {
+                        self.send_event(command, stdout, TerraformSourceStream::Stdout, |stdout| {
+                            self.parser.parse_plan_stdout(stdout)
+                        });
+                    }
+                }
+            },
+            |stderr| {
+                if let Some(stderr) = stderr {
+                    self.send_event(command, stderr, TerraformSourceStream::Stderr, |stderr| {
+                        self.parser.parse_stderr(stderr)
+                    });
+                }
+            },
+        )?;
+
+        let mut context = self.with_outcome(command, context);
+        context.partial_plan = context
+            .stdout
+            .iter()
+            .any(|stdout| self.parser.is_partial_plan_notice(stdout));
+
+        if self.verify_plan && context.outcome != Some(RunOutcome::Failure) {
+            let show = self.capture_command("show", vec!["show", "-no-color", plan_path])?;
+            if show.exit_code != Some(0) {
+                return Err(Error::PlanCorrupt(self.process.working_directory().join(plan_path)));
+            }
+        }
+
+        Ok(context)
+    }
+
+    pub fn run_apply(&self, target_plan: P) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.run_apply_with_envs(target_plan, &HashMap::new())
+    }
+
+    pub fn run_apply_with_envs(&self, target_plan: P, envs: &HashMap<String, String>) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.apply(ApplyMode::Plan(target_plan.as_ref()), envs, &[])
+    }
+
+    // `extra_args` are passed as is (before the plan path), for flags without a dedicated option.
+    pub fn run_apply_with_args(&self, target_plan: P, extra_args: &[String]) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.apply(ApplyMode::Plan(target_plan.as_ref()), &HashMap::new(), extra_args)
+    }
+
+    // The variables are written to a private (0600 on Unix) temporary `.tfvars.json` file, removed once applied:
+    // Terraform only reads JSON variable files by extension, so `-var-file=/dev/stdin` can't be used.
+    // A saved plan only accepts variables whose values are identical to the ones it was created with.
+    pub fn run_apply_with_stdin_vars(&self, target_plan: P, vars_json: &str) -> Result<ProcessContext, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let var_file = VarFile::create(vars_json)?;
+        let var_file_arg = format!("-var-file={}", var_file.path().to_str().ok_or(Error::PathError)?);
+
+        self.apply(ApplyMode::Plan(target_plan.as_ref()), &HashMap::new(), &[var_file_arg])
+    }
+
+    // A single entry point for direct and saved plan applies, instead of the `run_apply_*` variants.
+    pub fn run_apply_request(&self, request: &ApplyRequest) -> Result<ProcessContext, Error> {
+        let mode = match request.plan_file.as_ref() {
+            Some(plan_file) => ApplyMode::Plan(plan_file),
+            None => ApplyMode::Direct,
+        };
+
+        self.apply_with_flags(
+            mode,
+            &HashMap::new(),
+            &request.args()?,
+            &request.flags(&self.flags),
+            request.auto_approve.unwrap_or(self.auto_approve),
+        )
+    }
+
+    fn apply(
+        &self,
+        mode: ApplyMode,
+        envs: &HashMap<String, String>,
+        extra_args: &[String],
+    ) -> Result<ProcessContext, Error> {
+        self.apply_with_flags(mode, envs, extra_args, &self.flags, self.auto_approve)
+    }
+
+    // Without a saved plan, Terraform shows the changes and asks for confirmation, unless approval is automatic.
+    fn apply_with_flags(
+        &self,
+        mode: ApplyMode,
+        envs: &HashMap<String, String>,
+        extra_args: &[String],
+        flags: &CommonFlags,
+        auto_approve: bool,
+    ) -> Result<ProcessContext, Error> {
+        if self.read_only {
+            return Err(Error::OperationForbidden);
+        }
+
+        let command: &str = "apply";
+        let plan_path = match mode {
+            ApplyMode::Plan(path) => Some(path.to_str().ok_or(Error::PathError)?),
+            ApplyMode::Direct | ApplyMode::RefreshOnly => None,
+        };
+        let mut error: Option<String> = None;
+        let mut outputs: Option<Vec<(String, String)>> = None;
+
+        let prompted = plan_path.is_none() && !auto_approve;
+        if prompted && !self.can_answer_prompt() {
+            return Err(Error::ApprovalUnsupported);
+        }
+
+        if let Some(plan_path) = plan_path.filter(|_| !auto_approve) {
+            let plan = self.capture_command("show", vec!["show", "-no-color", plan_path])?;
+            if !self.approve(&plan.stdout_string()) {
+                return Err(Error::ApprovalDenied);
+            }
+        }
+
+        let mut shown = String::new();
+        let mut approved: Option<bool> = None;
+
+        let args = self.apply_args(mode, flags.input || prompted, extra_args, flags, auto_approve)?;
+
+        let mut context = self.spawn(args.iter().map(String::as_str).collect(), envs)?;
+        let mut stdin = context.take_stdin().filter(|_| prompted);
+
+        let result = context
+            .wait(
+                |stdout| {
+                    if let Some(stdout) = stdout {
+                        if stdin.is_some() {
+                            self.answer_approval_prompt(
+                                stdout.as_str(),
+                                &mut shown,
+                                &mut approved,
+                                &mut stdin,
+                                |plan| self.approve(plan),
+                            );
+                        }
+                        self.parser.collect_output(stdout.as_str(), &mut outputs);
+                        self.send_event(command, stdout, TerraformSourceStream::Stdout, |stdout| {
+                            self.parser.parse_apply_stdout(stdout)
+                        });
+                    }
+                },
+                |stderr| {
+                    if let Some(stderr) = stderr {
+                        self.send_event(command, stderr, TerraformSourceStream::Stderr, |stderr| {
+                            self.parser.parse_apply_stderr(stderr, &mut error)
+                        });
+                    }
+                },
+            )
+            .map(|context| self.with_outcome(command, context));
+
+        if let ApplyMode::Plan(target_plan) = mode {
+            if self.remove_plan_on_failure
+                && !matches!(&result, Ok(context) if context.outcome == Some(RunOutcome::Success))
+            {
+                if let Some(event) = self.remove_failed_plan(command, target_plan) {
+                    self.sender.send(event);
+                }
+            }
+        }
+        let context = result?;
+
+        if approved == Some(false) {
+            return Err(Error::ApprovalDenied);
+        }
+
+        if let Some(outputs) = outputs.filter(|outputs| !outputs.is_empty()) {
+            self.sender.send(TerraformEvent {
+                outputs: outputs
+                    .into_iter()
+                    .map(|(name, value)| (name, Parser::unquote_output(value)))
+                    .collect(),
+                matched: true,
+                stack: self.stack.clone(),
+                command: String::from(command),
+                ..TerraformEvent::default()
+            });
+        }
+
+        Ok(context)
+    }
+
+    fn apply_args(
+        &self,
+        mode: ApplyMode,
+        input: bool,
+        extra_args: &[String],
+        flags: &CommonFlags,
+        auto_approve: bool,
+    ) -> Result<Vec<String>, Error> {
+        let plan_path = match mode {
+            ApplyMode::Plan(path) => Some(path.to_str().ok_or(Error::PathError)?),
+            ApplyMode::Direct | ApplyMode::RefreshOnly => None,
+        };
+
+        let mut args = vec![String::from("apply")];
+        args.extend(self.common_args_with_flags("apply", input, flags));
+        if auto_approve {
+            args.push(String::from("-auto-approve"));
+        }
+        if let ApplyMode::RefreshOnly = mode {
+            args.push(String::from("-refresh-only"));
+        }
+        if self.quiet {
+            args.push(String::from("-compact-warnings"));
+        }
+        if self.json_output {
+            args.push(String::from("-json"));
+        }
+        args.extend(self.state_args(true)?);
+        args.extend(extra_args.iter().cloned());
+        args.extend(plan_path.map(String::from));
+
+        Ok(args)
+    }
+
+    // Updates the state to match the real infrastructure (e.g. to accept drift), without changing any resource.
+    pub fn run_apply_refresh_only(&self) -> Result<ProcessContext, Error> {
+        self.apply(ApplyMode::RefreshOnly, &HashMap::new(), &[])
+    }
+
+    pub fn run_destroy(&self) -> Result<ProcessContext, Error> {
+        self.run_destroy_with_envs(&HashMap::new())
+    }
+
+    pub fn run_destroy_with_envs(&self, envs: &HashMap<String, String>) -> Result<ProcessContext, Error> {
+        self.destroy(envs, &[])
+    }
+
+    // `extra_args` are passed as is, for flags without a dedicated option.
+    pub fn run_destroy_with_args(&self, extra_args: &[String]) -> Result<ProcessContext, Error> {
+        self.destroy(&HashMap::new(), extra_args)
+    }
+
+    fn destroy(&self, envs: &HashMap<String, String>, extra_args: &[String]) -> Result<ProcessContext, Error> {
+        if self.read_only {
+            return Err(Error::OperationForbidden);
+        }
+
+        let command: &str = "destroy";
+        let mut error: Option<String> = None;
+
+        let auto_approve = match &self.destroy_confirmation {
+            DestroyConfirm::Approval => self.auto_approve,
+            DestroyConfirm::AutoApprove => true,
+            DestroyConfirm::RequirePhrase(_) => false,
+        };
+        if !auto_approve && !self.can_answer_prompt() {
+            return Err(Error::ApprovalUnsupported);
+        }
+
+        let common_args = self.common_args(command, self.flags.input || !auto_approve);
+        let state_args = self.state_args(true)?;
+
+        let mut shown = String::new();
+        let mut approved: Option<bool> = None;
+
+        let mut args = vec!["destroy"];
+        args.extend(common_args.iter().map(String::as_str));
+        if auto_approve {
+            args.push("-auto-approve");
+        }
+        if self.quiet {
+            args.push("-compact-warnings");
+        }
+        if self.json_output {
+            args.push("-json");
+        }
+        args.extend(state_args.iter().map(String::as_str));
+        args.extend(extra_args.iter().map(String::as_str));
+
+        let mut context = self.spawn(args, envs)?;
+        let mut stdin = context.take_stdin().filter(|_| !auto_approve);
+
+        let context = context.wait(
+            |stdout| {
+                if let Some(stdout) = stdout {
+                    if stdin.is_some() {
+                        self.answer_approval_prompt(stdout.as_str(), &mut shown, &mut approved, &mut stdin, |plan| {
+                            self.confirm_destroy(plan)
+                        });
+                    }
+                    self.send_event(command, stdout, TerraformSourceStream::Stdout, |stdout| {
+                        self.parser.parse_apply_stdout(stdout)
+                    });
+                }
+            },
+            |stderr| {
+                if let Some(stderr) = stderr {
+                    self.send_event(command, stderr, TerraformSourceStream::Stderr, |stderr| {
+                        self.parser.parse_apply_stderr(stderr, &mut error)
+                    });
+                }
+            },
+        )?;
+
+        if approved == Some(false) {
+            return Err(Error::ApprovalDenied);
+        }
+
+        Ok(self.with_outcome(command, context))
+    }
+
+    // The confirmation prompt is answered from the piped output, and Terraform refuses to prompt in JSON mode.
+    fn can_answer_prompt(&self) -> bool {
+        !self.process.inherit_output() && !self.json_output
+    }
+
+    // Best effort: failing to remove the plan mustn't hide the outcome of the apply. The event reports the removal.
+    fn remove_failed_plan(&self, command: &str, target_plan: &Path) -> Option<TerraformEvent> {
+        let path = self.process.working_directory().join(target_plan);
+        std::fs::remove_file(&path).ok()?;
+
+        Some(TerraformEvent {
+            status: Some(TerraformResourceStatus::Notice),
+            message: Some(format!(
+                "Removed the plan file {} after the failed apply",
+                path.display()
+            )),
+            matched: true,
+            stack: self.stack.clone(),
+            command: String::from(command),
+            ..TerraformEvent::default()
+        })
+    }
+
+    fn send_event<F>(&self, command: &str, line: String, source_stream: TerraformSourceStream, parse: F)
+    where
+        F: FnOnce(String) -> TerraformEvent,
+    {
+        if let Some(event) = self.event(command, line, source_stream, parse) {
+            self.sender.send(event);
+        }
+    }
+
+    // The user line parser gets the first chance at each line, the built-in `parse` is the fallback. `None` for the
+    // lines skipped in quiet mode.
+    fn event<F>(
+        &self,
+        command: &str,
+        line: String,
+        source_stream: TerraformSourceStream,
+        parse: F,
+    ) -> Option<TerraformEvent>
+    where
+        F: FnOnce(String) -> TerraformEvent,
+    {
+        if self.quiet && self.parser.quiet_skipped_regex.is_match(line.as_str()) {
+            return None;
+        }
+
+        let event = match self
+            .line_parser
+            .as_ref()
+            .and_then(|parser| parser.parse(&line, command))
+        {
+            Some(event) => TerraformEvent {
+                source: line,
+                source_stream,
+                ..event
+            },
+            None => parse(line),
+        };
+
+        Some(TerraformEvent {
+            stack: self.stack.clone(),
+            command: String::from(command),
+            ..event.with_parsed_address()
+        })
+    }
+
+    fn approve(&self, plan: &str) -> bool {
+        self.approval_callback
+            .as_ref()
+            .map(|approval_callback| approval_callback(plan))
+            .unwrap_or(false)
+    }
+
+    fn confirm_destroy(&self, plan: &str) -> bool {
+        match &self.destroy_confirmation {
+            DestroyConfirm::RequirePhrase(phrase) => self
+                .confirmation_provider
+                .as_ref()
+                .and_then(|confirmation_provider| confirmation_provider(plan))
+                .map(|typed| typed.trim() == phrase)
+                .unwrap_or(false),
+            _ => self.approve(plan),
+        }
+    }
+
+    // Collects the output until the confirmation prompt, then answers it with the `approve` decision.
+    fn answer_approval_prompt<F>(
+        &self,
+        stdout: &str,
+        shown: &mut String,
+        approved: &mut Option<bool>,
+        stdin: &mut Option<ChildStdin>,
+        approve: F,
+    ) where
+        F: FnOnce(&str) -> bool,
+    {
+        if !self.parser.approval_prompt_regex.is_match(stdout) {
+            shown.push_str(stdout);
+            shown.push('\n');
+            return;
+        }
+
+        let approval = approve(shown);
+        if let Some(writer) = stdin.as_mut() {
+            let _ = writeln!(writer, "{}", if approval { "yes" } else { "no" }).and_then(|_| writer.flush());
+        }
+        *approved = Some(approval);
+        *stdin = None;
+    }
+
+    fn answer_input_prompt(&self, stdout: &str, stdin: &mut Option<ChildStdin>) {
+        if let (Some(input_provider), Some(captures)) =
+            (&self.input_provider, self.parser.input_prompt_regex.captures(stdout))
+        {
+            let name = captures.name("name").map(|m| m.as_str()).unwrap_or_default();
+
+            match (input_provider(name), stdin.as_mut()) {
+                (Some(value), Some(writer)) => {
+                    let _ = writeln!(writer, "{}", value).and_then(|_| writer.flush());
+                }
+                _ => *stdin = None,
+            }
+        }
+    }
+
+    fn spawn(&self, args: Vec<&str>, envs: &HashMap<String, String>) -> Result<ProcessContext, Error> {
+        Ok(self.process.spawn_with_envs(self.with_default_args(args), envs)?)
+    }
+
+    fn with_default_args<'a>(&'a self, args: Vec<&'a str>) -> Vec<&'a str> {
+        let subcommand_length = args.iter().take_while(|arg| !arg.starts_with('-')).count();
+        let (subcommand, rest) = args.split_at(subcommand_length);
+
+        match self.default_args.get(&subcommand.join(" ")) {
+            Some(default_args) => subcommand
+                .iter()
+                .copied()
+                .chain(default_args.iter().map(String::as_str))
+                .chain(rest.iter().copied())
+                .collect(),
+            None => args,
+        }
+    }
+
+    fn capture_command(&self, command: &str, args: Vec<&str>) -> Result<ProcessContext, Error> {
+        self.capture_command_with_input(command, args, None)
+    }
+
+    fn capture_command_with_input(
+        &self,
+        command: &str,
+        args: Vec<&str>,
+        input: Option<&str>,
+    ) -> Result<ProcessContext, Error> {
+        let mut context = self.spawn(args, &HashMap::new())?;
+        if let (Some(input), Some(mut stdin)) = (input, context.take_stdin()) {
+            stdin.write_all(input.as_bytes())?;
+        }
+
+        let context = context.wait(
+            |_| {},
+            |stderr| {
+                if let Some(stderr) = stderr {
+                    self.send_event(command, stderr, TerraformSourceStream::Stderr, |stderr| {
+                        self.parser.parse_stderr(stderr)
+                    });
+                }
+            },
+        )?;
+
+        Ok(self.with_outcome(command, context))
+    }
+
+    fn with_outcome(&self, command: &str, mut context: ProcessContext) -> ProcessContext {
+        context.outcome = Some(self.exit_code_policy.outcome(command, context.exit_code));
+        context
+    }
+}
+
+fn next_backoff(delay: Duration, backoff: Duration) -> Duration {
+    delay.saturating_mul(2).min(MAX_RETRY_BACKOFF.max(backoff))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::sync::Mutex;
+
+    // Serializes the tests spawning processes: a script still open for writing in a process forked by another test
+    // can't be executed ("Text file busy"), and a simulated Ctrl-C would interrupt the commands of other tests.
+    pub(crate) static PROCESSES: Mutex<()> = Mutex::new(());
+
+    // A working directory with a `terraform` shell script standing in for the real binary.
+    pub(crate) fn fake_terraform(name: &str, script: &str) -> (Terraform<PathBuf, PathBuf>, Receiver<TerraformEvent>) {
+        let directory = std::env::temp_dir().join(format!("terraform-rs-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let binary = directory.join("terraform");
+        std::fs::write(&binary, format!("#!/bin/sh\n{}", script)).unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let (sender, receiver) = channel();
+        let terraform = Terraform::new(binary, directory, HashMap::new(), Duration::from_secs(10), sender).unwrap();
+
+        (terraform, receiver)
+    }
+
+    #[test]
+    fn forbid_destroy_rejects_json_plan_deleting_a_resource() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut terraform, _events) = fake_terraform(
+            "json-plan-delete",
+            r#"cat <<'EOF'
+{"@level":"info","@message":"null_resource.kept: Plan to create","type":"planned_change","change":{"resource":{"addr":"null_resource.kept"},"action":"create"}}
+{"@level":"info","@message":"null_resource.gone: Plan to delete","type":"planned_change","change":{"resource":{"addr":"null_resource.gone"},"action":"delete"}}
+{"@level":"info","@message":"Plan: 1 to add, 0 to change, 1 to destroy.","type":"change_summary","changes":{"add":1,"change":0,"remove":1,"import":0,"operation":"plan"}}
+EOF
+exit 2
+"#,
+        );
+        terraform.set_json_output(true);
+
+        match terraform.run_plan_checked(PathBuf::from("plan.tfplan"), true) {
+            Err(Error::DestructivePlan(addresses)) => assert_eq!(addresses, vec![String::from("null_resource.gone")]),
+            result => panic!("unexpected result: {:?}", result.map(|context| context.stdout.clone())),
+        }
+        assert!(terraform.run_plan_checked(PathBuf::from("plan.tfplan"), false).is_ok());
+    }
+
+    #[test]
+    fn failed_plan_is_reported_before_missing_plan_file() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (terraform, _events) = fake_terraform("plan-failed", "echo 'Error: Invalid reference' >&2\nexit 1\n");
+
+        assert!(matches!(
+            terraform.run_plan_with_file_info(PathBuf::from("plan.tfplan")),
+            Err(Error::CommandFailed(Some(1)))
+        ));
+
+        let (terraform, _events) = fake_terraform("plan-not-written", "exit 0\n");
+        assert!(matches!(
+            terraform.run_plan_with_file_info(PathBuf::from("plan.tfplan")),
+            Err(Error::PlanFileMissing(_))
+        ));
+    }
+
+    #[test]
+    fn failed_graph_is_an_error() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (terraform, _events) = fake_terraform(
+            "graph-failed",
+            "echo 'digraph {'\necho 'Error: Module not installed' >&2\nexit 1\n",
+        );
+
+        assert!(matches!(terraform.run_graph(), Err(Error::CommandFailed(Some(1)))));
+    }
+
+    #[test]
+    fn read_only_forbids_workspace_delete() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut terraform, _events) = fake_terraform("workspace-delete", "touch deleted\n");
+        terraform.set_read_only(true);
+
+        assert!(matches!(
+            terraform.workspace_delete("staging", true),
+            Err(Error::OperationForbidden)
+        ));
+        assert!(!terraform.process.working_directory().join("deleted").exists());
+    }
+
+    #[test]
+    fn bom_is_only_stripped_from_the_first_line() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (terraform, events) = fake_terraform(
+            "bom",
+            "printf '\\357\\273\\277Plan: 1 to add, 0 to change, 0 to destroy.\\n\\357\\273\\277Done.\\n'\n",
+        );
+
+        let context = terraform.run_plan(PathBuf::from("plan.tfplan")).unwrap();
+        assert_eq!(
+            context.stdout,
+            vec![
+                String::from("Plan: 1 to add, 0 to change, 0 to destroy."),
+                String::from("\u{FEFF}Done."),
+            ]
+        );
+
+        let event = events.try_iter().next().unwrap();
+        assert_eq!(event.status, Some(TerraformResourceStatus::Completed));
+        assert_eq!(event.create_count, Some(1));
+        assert!(!event.source.starts_with('\u{FEFF}'));
+    }
+
+    #[test]
+    fn retry_backoff_is_capped_after_many_attempts() {
+        let backoff = Duration::from_secs(1);
+        let mut delay = backoff;
+        for _ in 0..1000 {
+            delay = next_backoff(delay, backoff);
+            assert!(delay <= MAX_RETRY_BACKOFF);
+        }
+        assert_eq!(delay, MAX_RETRY_BACKOFF);
+
+        assert_eq!(next_backoff(Duration::MAX, Duration::MAX), Duration::MAX);
+    }
+
+    #[test]
+    fn workspace_names_must_not_need_escaping() {
+        let (mut terraform, _events) = fake_terraform("workspace-names", "exit 0\n");
+
+        for name in ["staging", "eu-west-1_blue", "a~b.c", "team@prod", "a$b&c+d=e:f"].iter() {
+            assert!(terraform.with_workspace(name).is_ok(), "{}", name);
+        }
+        for name in ["", "a,b", "a;b", "a/b", "a?b", "a b", "a%20b"].iter() {
+            assert!(
+                matches!(terraform.with_workspace(name), Err(Error::InvalidWorkspaceName(_))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn plan_request_command_line() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (terraform, _events) = fake_terraform("plan-request", "exit 0\n");
+
+        let mut request = PlanRequest::new("plan.tfplan");
+        request.targets.push(String::from("aws_instance.a"));
+        request.vars.insert(String::from("b"), String::from("2"));
+        request.vars.insert(String::from("a"), String::from("1"));
+        request.refresh = Some(false);
+        request.parallelism = Some(3);
+
+        let context = terraform.run_plan_request(&request).unwrap();
+        let args: Vec<String> = context
+            .args()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            args,
+            vec![
+                "plan",
+                "-out=plan.tfplan",
+                "-no-color",
+                "-input=false",
+                "-parallelism=3",
+                "-refresh=false",
+                "-target=aws_instance.a",
+                "-var=a=1",
+                "-var=b=2",
+            ]
+        );
+    }
+
+    #[test]
+    fn inherited_output_rejects_approval_prompts_before_spawning() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut terraform, _events) = fake_terraform("inherit-prompt", "touch spawned\n");
+        let spawned = terraform.process.working_directory().join("spawned");
+        terraform.process.set_inherit_output(true);
+        terraform.set_auto_approve(false);
+
+        assert!(matches!(terraform.run_destroy(), Err(Error::ApprovalUnsupported)));
+        assert!(matches!(
+            terraform.run_apply_refresh_only(),
+            Err(Error::ApprovalUnsupported)
+        ));
+        assert!(!spawned.exists());
+
+        terraform.set_auto_approve(true);
+        assert!(terraform.run_destroy().is_ok());
+        assert!(spawned.exists());
+    }
+
+    #[test]
+    fn json_output_rejects_approval_prompts_before_spawning() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut terraform, _events) = fake_terraform("json-prompt", "touch spawned\n");
+        let spawned = terraform.process.working_directory().join("spawned");
+        terraform.set_json_output(true);
+        terraform.set_auto_approve(false);
+
+        assert!(matches!(terraform.run_destroy(), Err(Error::ApprovalUnsupported)));
+        assert!(matches!(
+            terraform.run_apply_refresh_only(),
+            Err(Error::ApprovalUnsupported)
+        ));
+        assert!(!spawned.exists());
     }
 }
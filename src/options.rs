@@ -0,0 +1,336 @@
+use crate::errors::Error;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    // When false, `-backend=false` skips the backend configuration: only providers and modules are installed.
+    pub backend: bool,
+    // Shared provider cache passed as `TF_PLUGIN_CACHE_DIR`, created if missing. Relative to the working directory.
+    pub plugin_cache_dir: Option<PathBuf>,
+    // `-migrate-state`: moves the existing state when the backend configuration changed.
+    pub migrate_state: bool,
+    // `-force-copy`: answers "yes" to the state migration prompts, which also implies `-migrate-state`. Enabled by
+    // default for compatibility, disable it so a changed backend fails instead of being migrated unattended.
+    pub force_copy: bool,
+    // `-upgrade`: installs the newest allowed provider and module versions, updating the dependency lock file.
+    pub upgrade: bool,
+    // Backend settings (e.g. a per-branch state key), passed with `-backend-config` through a temporary file removed
+    // once init is over, so secret values don't appear in the process list.
+    pub backend_config: HashMap<String, String>,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            backend: true,
+            plugin_cache_dir: None,
+            migrate_state: false,
+            force_copy: true,
+            upgrade: false,
+            backend_config: HashMap::new(),
+        }
+    }
+}
+
+// Global flags shared by init, plan, apply and destroy (each command only gets the ones it supports).
+#[derive(Debug, Clone)]
+pub struct CommonFlags {
+    // The built-in parsing expects uncolored output, only disable it when the output is displayed as is.
+    pub no_color: bool,
+    // Lets Terraform prompt for missing values; plan enables it anyway when an input provider is set, and destroy
+    // when approval isn't automatic.
+    pub input: bool,
+    pub lock: bool,
+    pub lock_timeout: Option<Duration>,
+    pub parallelism: Option<u32>,
+    pub refresh: bool,
+}
+
+impl Default for CommonFlags {
+    fn default() -> Self {
+        Self {
+            no_color: true,
+            input: false,
+            lock: true,
+            lock_timeout: None,
+            parallelism: None,
+            refresh: true,
+        }
+    }
+}
+
+// How destroy is confirmed, stricter than apply if need be.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DestroyConfirm {
+    // Same as apply: automatic unless disabled with `set_auto_approve`, then up to the approval callback.
+    #[default]
+    Approval,
+    // Always automatic, even when apply requires an approval.
+    AutoApprove,
+    // The confirmation provider must answer with this exact phrase (e.g. the workspace name) for the destroy to go
+    // ahead; anything else answers "no" to Terraform's prompt.
+    RequirePhrase(String),
+}
+
+// Everything a plan can be given at once, see `run_plan_request`. The flags left to `None` keep the value of the
+// instance's `CommonFlags`.
+#[derive(Debug, Clone, Default)]
+pub struct PlanRequest {
+    // Plan file to write, relative to the working directory.
+    pub out: PathBuf,
+    // `-target`: resource addresses to restrict the plan to.
+    pub targets: Vec<String>,
+    // `-replace`: resource addresses to replace even without changes.
+    pub replace: Vec<String>,
+    // `-var`, passed in name order.
+    pub vars: HashMap<String, String>,
+    // `-var-file`, relative to the working directory.
+    pub var_files: Vec<PathBuf>,
+    pub refresh: Option<bool>,
+    pub lock: Option<bool>,
+    pub parallelism: Option<u32>,
+    // `-destroy`: plans the destruction of every resource.
+    pub destroy: bool,
+}
+
+impl PlanRequest {
+    pub fn new<T: Into<PathBuf>>(out: T) -> Self {
+        Self {
+            out: out.into(),
+            ..Self::default()
+        }
+    }
+
+    // The request flags overriding `flags`.
+    pub(crate) fn flags(&self, flags: &CommonFlags) -> CommonFlags {
+        CommonFlags {
+            refresh: self.refresh.unwrap_or(flags.refresh),
+            lock: self.lock.unwrap_or(flags.lock),
+            parallelism: self.parallelism.or(flags.parallelism),
+            ..flags.clone()
+        }
+    }
+
+    // The arguments not covered by `CommonFlags`, in a stable order.
+    pub(crate) fn args(&self) -> Result<Vec<String>, Error> {
+        let mut args = Vec::new();
+        if self.destroy {
+            args.push(String::from("-destroy"));
+        }
+        args.extend(self.targets.iter().map(|target| format!("-target={}", target)));
+        args.extend(self.replace.iter().map(|replace| format!("-replace={}", replace)));
+
+        let mut names: Vec<&String> = self.vars.keys().collect();
+        names.sort();
+        args.extend(
+            names
+                .into_iter()
+                .map(|name| format!("-var={}={}", name, self.vars[name])),
+        );
+
+        for var_file in &self.var_files {
+            args.push(format!("-var-file={}", var_file.to_str().ok_or(Error::PathError)?));
+        }
+
+        Ok(args)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ApplyRequest {
+    // Saved plan to apply, relative to the working directory. Without it, the configuration is planned and applied in
+    // one go, after the confirmation prompt unless approval is automatic.
+    pub plan_file: Option<PathBuf>,
+    // `-target`: resource addresses to restrict the apply to. Not allowed with a saved plan.
+    pub targets: Vec<String>,
+    // `-replace`: resource addresses to replace even without changes. Not allowed with a saved plan.
+    pub replace: Vec<String>,
+    // `-var`, passed in name order. Not allowed with a saved plan.
+    pub vars: HashMap<String, String>,
+    pub parallelism: Option<u32>,
+    pub lock: Option<bool>,
+    // Overrides the approval of the `Terraform` instance.
+    pub auto_approve: Option<bool>,
+}
+
+impl ApplyRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_plan_file<T: Into<PathBuf>>(plan_file: T) -> Self {
+        Self {
+            plan_file: Some(plan_file.into()),
+            ..Self::default()
+        }
+    }
+
+    // The request flags overriding `flags`.
+    pub(crate) fn flags(&self, flags: &CommonFlags) -> CommonFlags {
+        CommonFlags {
+            lock: self.lock.unwrap_or(flags.lock),
+            parallelism: self.parallelism.or(flags.parallelism),
+            ..flags.clone()
+        }
+    }
+
+    // The arguments not covered by `CommonFlags`, in a stable order. Terraform rejects the planning options along
+    // with a saved plan, as they were fixed when the plan was created.
+    pub(crate) fn args(&self) -> Result<Vec<String>, Error> {
+        if self.plan_file.is_some() {
+            let mut options = Vec::new();
+            if !self.targets.is_empty() {
+                options.push("-target");
+            }
+            if !self.replace.is_empty() {
+                options.push("-replace");
+            }
+            if !self.vars.is_empty() {
+                options.push("-var");
+            }
+            if !options.is_empty() {
+                return Err(Error::InvalidRequest(format!(
+                    "{} can't be combined with a saved plan file",
+                    options.join(", ")
+                )));
+            }
+        }
+
+        let mut args = Vec::new();
+        args.extend(self.targets.iter().map(|target| format!("-target={}", target)));
+        args.extend(self.replace.iter().map(|replace| format!("-replace={}", replace)));
+
+        let mut names: Vec<&String> = self.vars.keys().collect();
+        names.sort();
+        args.extend(
+            names
+                .into_iter()
+                .map(|name| format!("-var={}={}", name, self.vars[name])),
+        );
+
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    #[test]
+    fn plan_request_args_are_ordered() {
+        let mut request = PlanRequest::new("plan.tfplan");
+        request.destroy = true;
+        request.targets = strings(&["module.a", "aws_instance.b"]);
+        request.replace = strings(&["aws_instance.c"]);
+        request.vars.insert(String::from("zone"), String::from("b"));
+        request.vars.insert(String::from("name"), String::from("x=y"));
+        request.vars.insert(String::from("count"), String::from("2"));
+        request.var_files = vec![PathBuf::from("a.tfvars"), PathBuf::from("b.tfvars")];
+
+        assert_eq!(
+            request.args().unwrap(),
+            strings(&[
+                "-destroy",
+                "-target=module.a",
+                "-target=aws_instance.b",
+                "-replace=aws_instance.c",
+                "-var=count=2",
+                "-var=name=x=y",
+                "-var=zone=b",
+                "-var-file=a.tfvars",
+                "-var-file=b.tfvars",
+            ])
+        );
+        assert!(PlanRequest::new("plan.tfplan").args().unwrap().is_empty());
+    }
+
+    #[test]
+    fn plan_request_flags_override_the_common_flags() {
+        let flags = CommonFlags {
+            parallelism: Some(10),
+            lock_timeout: Some(Duration::from_secs(30)),
+            ..CommonFlags::default()
+        };
+
+        let unset = PlanRequest::new("plan.tfplan").flags(&flags);
+        assert!(unset.refresh && unset.lock);
+        assert_eq!(unset.parallelism, Some(10));
+
+        let mut request = PlanRequest::new("plan.tfplan");
+        request.refresh = Some(false);
+        request.lock = Some(false);
+        request.parallelism = Some(2);
+
+        let overridden = request.flags(&flags);
+        assert!(!overridden.refresh && !overridden.lock);
+        assert_eq!(overridden.parallelism, Some(2));
+        assert_eq!(overridden.lock_timeout, Some(Duration::from_secs(30)));
+        assert!(overridden.no_color);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn plan_request_rejects_non_utf8_var_files() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut request = PlanRequest::new("plan.tfplan");
+        request.var_files = vec![PathBuf::from(OsStr::from_bytes(b"vars-\xff.tfvars"))];
+
+        assert!(matches!(request.args(), Err(Error::PathError)));
+    }
+
+    #[test]
+    fn apply_request_args_are_ordered() {
+        let mut request = ApplyRequest::new();
+        request.targets = strings(&["aws_instance.a"]);
+        request.replace = strings(&["aws_instance.b"]);
+        request.vars.insert(String::from("b"), String::from("2"));
+        request.vars.insert(String::from("a"), String::from("1"));
+
+        assert_eq!(
+            request.args().unwrap(),
+            strings(&[
+                "-target=aws_instance.a",
+                "-replace=aws_instance.b",
+                "-var=a=1",
+                "-var=b=2"
+            ])
+        );
+    }
+
+    #[test]
+    fn apply_request_rejects_planning_options_with_a_saved_plan() {
+        assert!(ApplyRequest::with_plan_file("plan.tfplan").args().unwrap().is_empty());
+
+        let mut request = ApplyRequest::with_plan_file("plan.tfplan");
+        request.targets = strings(&["aws_instance.a"]);
+        match request.args() {
+            Err(Error::InvalidRequest(message)) => assert!(message.starts_with("-target "), "{}", message),
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let mut request = ApplyRequest::with_plan_file("plan.tfplan");
+        request.replace = strings(&["aws_instance.a"]);
+        request.vars.insert(String::from("a"), String::from("1"));
+        match request.args() {
+            Err(Error::InvalidRequest(message)) => assert!(message.starts_with("-replace, -var "), "{}", message),
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // not planning options, applied along with the saved plan
+        let mut request = ApplyRequest::with_plan_file("plan.tfplan");
+        request.parallelism = Some(2);
+        request.lock = Some(false);
+        assert!(request.args().is_ok());
+        let flags = request.flags(&CommonFlags::default());
+        assert!(!flags.lock);
+        assert_eq!(flags.parallelism, Some(2));
+    }
+}
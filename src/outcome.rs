@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Success,
+    // The command succeeded and reported pending changes (plan with `-detailed-exitcode`).
+    ChangesPresent,
+    Failure,
+}
+
+// How each command's exit code is interpreted: 0 is a success and any other code (or none, when killed) a failure,
+// except for the codes registered as "changes present".
+#[derive(Debug, Clone)]
+pub struct ExitCodePolicy {
+    changes_present: HashMap<String, i32>,
+}
+
+impl Default for ExitCodePolicy {
+    fn default() -> Self {
+        let mut changes_present = HashMap::new();
+        changes_present.insert(String::from("plan"), 2);
+
+        Self { changes_present }
+    }
+}
+
+impl ExitCodePolicy {
+    // Commands are named like default arguments, with nested subcommand words joined by spaces.
+    pub fn set_changes_present(&mut self, command: &str, exit_code: Option<i32>) {
+        match exit_code {
+            Some(exit_code) => self.changes_present.insert(String::from(command), exit_code),
+            None => self.changes_present.remove(command),
+        };
+    }
+
+    pub fn outcome(&self, command: &str, exit_code: Option<i32>) -> RunOutcome {
+        match exit_code {
+            Some(0) => RunOutcome::Success,
+            Some(code) if self.changes_present.get(command) == Some(&code) => RunOutcome::ChangesPresent,
+            _ => RunOutcome::Failure,
+        }
+    }
+}
@@ -0,0 +1,1117 @@
+use crate::diagnostic::TerraformDiagnostic;
+use crate::errors::Error;
+use crate::event::{
+    TerraformEvent, TerraformRemediation, TerraformResourceChange, TerraformResourceStatus, TerraformSourceStream,
+};
+use crate::json::JsonMessage;
+use crate::process::ProcessContext;
+use crate::test_report::TestReport;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// Lets users recognize lines the built-in parsing doesn't know about, such as provider-specific progress output.
+// Returning `None` falls back to the built-in parsing; `source`, `source_stream` and `command` are filled in by the caller.
+pub trait LineParser {
+    fn parse(&self, line: &str, command: &str) -> Option<TerraformEvent>;
+}
+
+// The built-in parsing of Terraform's output.
+pub(crate) struct Parser {
+    pub(crate) init_completed_regex: Regex,
+    pub(crate) backend_changed_regex: Regex,
+    pub(crate) plan_change_regex: Regex,
+    pub(crate) plan_drift_regex: Regex,
+    pub(crate) refreshing_regex: Regex,
+    pub(crate) pre_apply_regex: Regex,
+    pub(crate) still_applying_regex: Regex,
+    pub(crate) post_apply_regex: Regex,
+    pub(crate) plan_completed_regex: Regex,
+    pub(crate) plan_no_changes_regex: Regex,
+    pub(crate) apply_completed_regex: Regex,
+    pub(crate) destroy_completed_regex: Regex,
+    pub(crate) state_lock_regex: Regex,
+    pub(crate) error_regex: Regex,
+    pub(crate) error_address_regex: Regex,
+    pub(crate) input_prompt_regex: Regex,
+    pub(crate) approval_prompt_regex: Regex,
+    pub(crate) transient_error_regex: Regex,
+    pub(crate) test_run_regex: Regex,
+    pub(crate) test_error_run_regex: Regex,
+    pub(crate) graph_edge_regex: Regex,
+    pub(crate) upgrade_notice_regex: Regex,
+    pub(crate) partial_plan_regex: Regex,
+    pub(crate) module_download_regex: Regex,
+    pub(crate) module_installed_regex: Regex,
+    pub(crate) required_version_regex: Regex,
+    pub(crate) locked_provider_regex: Regex,
+    pub(crate) locked_version_regex: Regex,
+    pub(crate) state_completed_regex: Regex,
+    pub(crate) quiet_skipped_regex: Regex,
+    pub(crate) generated_config_regex: Regex,
+    pub(crate) output_regex: Regex,
+}
+
+impl Parser {
+    pub(crate) fn new() -> Result<Self, Error> {
+        Ok(Self {
+            init_completed_regex: Regex::new(r"^Terraform has been successfully initialized!")?,
+            // "Terraform has detected that the configuration specified for the backend"
+            backend_changed_regex: Regex::new(r"^Terraform has detected that the configuration specified for the backend")?,
+            // "  # %s will be created"
+            // "  # %s will be read during apply"
+            // "  # %s will be updated in-place"
+            // "  # %s will be destroyed"
+            // "  # %s is tainted, so must be replaced"
+            // "  # %s must be replaced"
+            // "  # %s will be replaced, as requested"
+            // "  # %s will be replaced due to changes in replace_triggered_by"
+            plan_change_regex: Regex::new(
+                "  # (?P<address>.+?) ((will be ((?P<action_create>created)|((?P<action_read>read) during apply)|((?P<action_update>updated) in-place)|(?P<action_destroy>destroyed)))|(((is tainted, so )?must|will) be (?P<action_replace>replaced)))"
+            )?,
+            // "Note: Objects have changed outside of Terraform" section:
+            // "  # %s has changed"
+            // "  # %s has been deleted"
+            plan_drift_regex: Regex::new(
+                "^  # (?P<address>.+) ((has (?P<action_update>changed))|(has been (?P<action_destroy>deleted)))$",
+            )?,
+            // "(addr): Refreshing state...( [key=value(, key=value)*])?"
+            refreshing_regex: Regex::new(
+                r"^(?P<address>.+?): Refreshing state\.\.\.(?: \[(?P<attributes>.+)\])?$",
+            )?,
+            // "(addr)( \(generation\))?: (Destroying|Creating|Modifying|Reading)...( [key=value(, key=value)*])?"
+            pre_apply_regex: Regex::new(
+                r"^(?P<address>.+?)( \((?P<generation>.*)\))?: (?P<action>(Destroying|Creating|Modifying|Reading))\.\.\.(?: \[(?P<attributes>.+)\])?$",
+            )?,
+            // "(addr)( \(generation\))?: Still (modifying|destroying|creating|reading)... [(key=value, )*(elapsed)]"
+            still_applying_regex: Regex::new(
+                r"^(?P<address>.+?)( \((?P<generation>.*)\))?: Still (?P<action>(modifying|destroying|creating|reading))\.\.\. \[(?:(?P<attributes>.+), )?(?P<elapsed>\d+\w+) elapsed\]",
+            )?,
+            // "(addr)( \(generation\))?: (Modifications|Destruction|Creation|Read) complete after (elapsed)( [key=value(, key=value)*])?"
+            post_apply_regex: Regex::new(
+                r"^(?P<address>.+?)( \((?P<generation>.*)\))?: (?P<action>(Modifications|Destruction|Creation|Read)) complete after (?P<elapsed>\d+\w+)(?: \[(?P<attributes>.+)\])?$",
+            )?,
+
+            // "Plan: ((count) to import, )(count) to add, (count) to change, (count) to destroy."
+            plan_completed_regex: Regex::new(r"Plan: (?:(?P<import_count>\d+) to import, )?(?P<add_count>\d+) to add, (?P<change_count>\d+) to change, (?P<destroy_count>\d+) to destroy.")?,
+            // "No changes. Your infrastructure matches the configuration."
+            plan_no_changes_regex: Regex::new(r"^No changes\. ")?,
+            // "Apply complete! Resources: ((count) imported, )(count) added, (count) changed, (count) destroyed."
+            apply_completed_regex: Regex::new(r"Apply complete! Resources: (?:(?P<import_count>\d+) imported, )?(?P<add_count>\d+) added, (?P<change_count>\d+) changed, (?P<destroy_count>\d+) destroyed.")?,
+            destroy_completed_regex: Regex::new(r"Destroy complete! Resources: (?P<destroy_count>\d+) destroyed.")?,
+            // "(Acquiring|Still acquiring|Releasing) state lock. This may take a few moments..."
+            state_lock_regex: Regex::new(r"^(?P<action>Acquiring|Still acquiring|Releasing) state lock")?,
+            // "Error: (message)"
+            error_regex: Regex::new(r"^Error: (?P<message>.+)$")?,
+            // "  with (addr),"
+            error_address_regex: Regex::new(r"^\s+with (?P<address>[^,]+),$")?,
+            // "var.(name)", followed by "  Enter a value: "
+            input_prompt_regex: Regex::new(r"^var\.(?P<name>\S+)$")?,
+            // "Do you really want to destroy all resources?" (or "... resources in workspace "(name)"?"),
+            // "Would you like to update the Terraform state to reflect these detected changes?" for refresh-only, and
+            // "Do you want to perform these actions?" (or "... actions in workspace "(name)"?") without a saved plan
+            approval_prompt_regex: Regex::new(
+                r"^(Do you really want to destroy all resources|Would you like to update the Terraform state|Do you want to perform these actions)",
+            )?,
+            // network and registry failures that are worth retrying
+            transient_error_regex: Regex::new(
+                r"(?i)(i/o timeout|timed out|TLS handshake timeout|connection reset by peer|connection refused|no such host|temporary failure in name resolution|429 Too Many Requests|502 Bad Gateway|503 Service Unavailable|504 Gateway Timeout)",
+            )?,
+            // "(file)... in progress", then "  run "(name)"... (pass|fail|skip|error)"
+            test_run_regex: Regex::new(r#"^\s+run "(?P<name>[^"]+)"\.\.\. (?P<result>pass|fail|skip|error)$"#)?,
+            // "  on (file) line (line), in run "(name)":"
+            test_error_run_regex: Regex::new(r#"^\s+on (?P<file>\S+) line \d+, in run "(?P<name>[^"]+)":$"#)?,
+            // "  "(addr)" -> "(dependency addr)"", with legacy "[root] (addr) (expand)" node names
+            graph_edge_regex: Regex::new(r#"^\s*"(?:\[root\] )?(?P<from>(?:[^"\\]|\\.)+?)(?: \((?:expand|close)\))?" -> "(?:\[root\] )?(?P<to>(?:[^"\\]|\\.)+?)(?: \((?:expand|close)\))?"(?: \[.*\])?;?$"#)?,
+            // "Your version of Terraform is out of date! The latest version"
+            upgrade_notice_regex: Regex::new(r"^Your version of Terraform is out of date!")?,
+            // "Warning: Resource targeting is in effect", or "- Resource targeting is in effect" with compact warnings
+            partial_plan_regex: Regex::new(r"^(Warning: |- )Resource targeting is in effect$")?,
+            // "Downloading (source)( (version))? for (module)..."
+            module_download_regex: Regex::new(
+                r"^Downloading (?P<source>\S+)(?: (?P<version>\S+))? for (?P<module>\S+)\.\.\.$",
+            )?,
+            // "- (module) in (path)"
+            module_installed_regex: Regex::new(r"^- (?P<module>\S+) in (?P<path>.+)$")?,
+            // "  required_version = "(constraint)"", in a `terraform {}` block (the only place it is allowed)
+            required_version_regex: Regex::new(r#"(?m)^\s*required_version\s*=\s*"(?P<constraint>[^"]*)""#)?,
+            // "provider "(source)" {", in ".terraform.lock.hcl"
+            locked_provider_regex: Regex::new(r#"^provider "(?P<source>[^"]+)" \{$"#)?,
+            // "  version     = "(version)"", in a provider block of ".terraform.lock.hcl"
+            locked_version_regex: Regex::new(r#"^\s+version\s*=\s*"(?P<version>[^"]*)"$"#)?,
+            // "Successfully moved 1 object(s)." or "Successfully removed 1 resource instance(s)."
+            state_completed_regex: Regex::new(r"^Successfully (moved|removed) \d+ ")?,
+            // "Terraform has generated configuration and written it to (path). Please"
+            generated_config_regex: Regex::new(
+                r"^Terraform has generated configuration and written it to (?P<path>\S+?)\.(\s|$)",
+            )?,
+            // "(name) = (value)", in the "Outputs:" section
+            output_regex: Regex::new(r"^(?P<name>[A-Za-z_][\w-]*) = (?P<value>.*)$")?,
+            // blank lines, separators ("─────", "-----") and "(addr): Refreshing state... [id=(id)]"
+            quiet_skipped_regex: Regex::new(r"^(\s*|[─\-=]+|.+: Refreshing state\.\.\..*)$")?,
+        })
+    }
+
+    // Compiled once, for parsing outside of a `Terraform` instance.
+    pub(crate) fn shared() -> &'static Parser {
+        static PARSER: OnceLock<Parser> = OnceLock::new();
+
+        PARSER.get_or_init(|| Parser::new().expect("built-in regexes are valid"))
+    }
+
+    // Parses captured output as the live parsing of `command` does, stdout lines first.
+    pub(crate) fn parse_captured(&self, command: &str, stdout: &[String], stderr: &[String]) -> Vec<TerraformEvent> {
+        let mut error: Option<String> = None;
+
+        stdout
+            .iter()
+            .cloned()
+            .map(|stdout| match command {
+                "init" => self.parse_init_stdout(stdout),
+                "get" => self.parse_get_stdout(stdout),
+                "plan" => self.parse_plan_stdout(stdout),
+                "apply" | "destroy" => self.parse_apply_stdout(stdout),
+                "state" => self.parse_state_stdout(stdout),
+                _ => TerraformEvent {
+                    source: stdout,
+                    ..TerraformEvent::default()
+                },
+            })
+            .chain(stderr.iter().cloned().map(|stderr| match command {
+                "init" => self.parse_init_stderr(stderr),
+                "apply" | "destroy" => self.parse_apply_stderr(stderr, &mut error),
+                _ => self.parse_stderr(stderr),
+            }))
+            .map(|event| TerraformEvent {
+                command: String::from(command),
+                ..event.with_parsed_address()
+            })
+            .collect()
+    }
+
+    pub(crate) fn parse_init_stdout(&self, stdout: String) -> TerraformEvent {
+        if self.init_completed_regex.is_match(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Completed),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if self.backend_changed_regex.is_match(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Notice),
+                message: Some(String::from("Backend configuration changed")),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else {
+            self.parse_get_stdout(stdout)
+        }
+    }
+
+    // Module installation, as printed by `get` and `init`: the module address is the resource path, and the module
+    // "source", "version" and local "path" are attributes.
+    pub(crate) fn parse_get_stdout(&self, stdout: String) -> TerraformEvent {
+        if let Some(captures) = self.module_download_regex.captures(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Started),
+                resource_path: captures.name("module").map(|m| module_address(m.as_str())),
+                attributes: ["source", "version"]
+                    .iter()
+                    .filter_map(|name| Some((String::from(*name), String::from(captures.name(name)?.as_str()))))
+                    .collect(),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.module_installed_regex.captures(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Done),
+                resource_path: captures.name("module").map(|m| module_address(m.as_str())),
+                attributes: captures
+                    .name("path")
+                    .map(|m| (String::from("path"), String::from(m.as_str())))
+                    .into_iter()
+                    .collect(),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else {
+            TerraformEvent {
+                source: stdout,
+                ..TerraformEvent::default()
+            }
+        }
+    }
+
+    pub(crate) fn parse_state_stdout(&self, stdout: String) -> TerraformEvent {
+        if self.state_completed_regex.is_match(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Completed),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else {
+            TerraformEvent {
+                source: stdout,
+                ..TerraformEvent::default()
+            }
+        }
+    }
+
+    // Stderr lines without a command specific meaning; upgrade notices are informational, not errors.
+    pub(crate) fn parse_stderr(&self, stderr: String) -> TerraformEvent {
+        if self.upgrade_notice_regex.is_match(stderr.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Notice),
+                message: Some(stderr.clone()),
+                source: stderr,
+                source_stream: TerraformSourceStream::Stderr,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.error_regex.captures(stderr.as_str()) {
+            TerraformEvent {
+                message: captures.name("message").map(|m| String::from(m.as_str().trim())),
+                remediation: captures.name("message").and_then(|m| remediation(m.as_str())),
+                source: stderr,
+                source_stream: TerraformSourceStream::Stderr,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else {
+            TerraformEvent {
+                source: stderr,
+                source_stream: TerraformSourceStream::Stderr,
+                ..TerraformEvent::default()
+            }
+        }
+    }
+
+    pub(crate) fn parse_init_stderr(&self, stderr: String) -> TerraformEvent {
+        if let Some(captures) = self.error_regex.captures(stderr.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Failed),
+                message: captures.name("message").map(|m| String::from(m.as_str().trim())),
+                remediation: captures.name("message").and_then(|m| remediation(m.as_str())),
+                source: stderr,
+                source_stream: TerraformSourceStream::Stderr,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else {
+            self.parse_stderr(stderr)
+        }
+    }
+
+    pub(crate) fn parse_plan_stdout(&self, stdout: String) -> TerraformEvent {
+        if let Some(event) = self.parse_json_stdout(stdout.as_str()) {
+            TerraformEvent {
+                partial_plan: self.is_partial_plan_notice(stdout.as_str()),
+                source: stdout,
+                ..event
+            }
+        } else if self.partial_plan_regex.is_match(stdout.as_str()) {
+            TerraformEvent {
+                partial_plan: true,
+                message: Some(String::from("Resource targeting is in effect")),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(status) = self.parse_lock_status(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(status),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(event) = self.parse_refreshing(stdout.as_str()) {
+            TerraformEvent {
+                source: stdout,
+                ..event
+            }
+        } else if let Some(captures) = self.plan_drift_regex.captures(stdout.as_str()) {
+            let (address, _, _) = self.parse_context_captures(&captures);
+
+            TerraformEvent {
+                change: self.captures_to_change(captures),
+                status: Some(TerraformResourceStatus::Drifted),
+                resource_path: address,
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.plan_change_regex.clone().captures(stdout.as_str()) {
+            let (address, _, _) = self.parse_context_captures(&captures);
+
+            TerraformEvent {
+                change: self.captures_to_change(captures),
+                status: Some(TerraformResourceStatus::Planned),
+                resource_path: address,
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.plan_completed_regex.clone().captures(stdout.as_str()) {
+            let (create_count, update_count, delete_count, import_count) = self.parse_stats_captures(&captures);
+
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Completed),
+                source: stdout,
+                create_count,
+                update_count,
+                delete_count,
+                import_count,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if self.plan_no_changes_regex.is_match(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Completed),
+                source: stdout,
+                create_count: Some(0),
+                update_count: Some(0),
+                delete_count: Some(0),
+                import_count: Some(0),
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.generated_config_regex.captures(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Notice),
+                generated_config: captures.name("path").map(|m| PathBuf::from(m.as_str())),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else {
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Planned),
+                source: stdout,
+                ..TerraformEvent::default()
+            }
+        }
+    }
+
+    #[allow(deprecated)]
+    pub(crate) fn parse_apply_stdout(&self, stdout: String) -> TerraformEvent {
+        if let Some(event) = self.parse_json_stdout(stdout.as_str()) {
+            TerraformEvent {
+                source: stdout,
+                ..event
+            }
+        } else if let Some(status) = self.parse_lock_status(stdout.as_str()) {
+            TerraformEvent {
+                status: Some(status),
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(event) = self.parse_refreshing(stdout.as_str()) {
+            TerraformEvent {
+                source: stdout,
+                ..event
+            }
+        } else if let Some(captures) = self.pre_apply_regex.clone().captures(stdout.as_str()) {
+            let (address, id_key, id_value) = self.parse_context_captures(&captures);
+            let attributes = self.parse_attributes_captures(&captures).into_iter().collect();
+
+            TerraformEvent {
+                change: self.captures_to_change(captures),
+                status: Some(TerraformResourceStatus::Started),
+                resource_path: address,
+                id_key,
+                id_value,
+                attributes,
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.still_applying_regex.clone().captures(stdout.as_str()) {
+            let (address, id_key, id_value) = self.parse_context_captures(&captures);
+            let attributes = self.parse_attributes_captures(&captures).into_iter().collect();
+            let elapsed = captures.name("elapsed").and_then(|m| parse_elapsed(m.as_str()));
+
+            TerraformEvent {
+                change: self.captures_to_change(captures),
+                status: Some(TerraformResourceStatus::InProgress),
+                resource_path: address,
+                id_key,
+                id_value,
+                attributes,
+                elapsed,
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.post_apply_regex.clone().captures(stdout.as_str()) {
+            let (address, id_key, id_value) = self.parse_context_captures(&captures);
+            let attributes = self.parse_attributes_captures(&captures).into_iter().collect();
+            let elapsed = captures.name("elapsed").and_then(|m| parse_elapsed(m.as_str()));
+
+            TerraformEvent {
+                change: self.captures_to_change(captures),
+                status: Some(TerraformResourceStatus::Done),
+                resource_path: address,
+                id_key,
+                id_value,
+                attributes,
+                elapsed,
+                source: stdout,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.apply_completed_regex.clone().captures(stdout.as_str()) {
+            let (create_count, update_count, delete_count, import_count) = self.parse_stats_captures(&captures);
+
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Completed),
+                source: stdout,
+                create_count,
+                update_count,
+                delete_count,
+                import_count,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self.destroy_completed_regex.clone().captures(stdout.as_str()) {
+            let (create_count, update_count, delete_count, import_count) = self.parse_stats_captures(&captures);
+
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Completed),
+                source: stdout,
+                create_count,
+                update_count,
+                delete_count,
+                import_count,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else {
+            TerraformEvent {
+                source: stdout,
+                ..TerraformEvent::default()
+            }
+        }
+    }
+
+    // In JSON mode, every line is a message; the "change_summary", "planned_change", resource progress of apply and
+    // applied "outputs" ones are interpreted, as the matching human readable lines would be.
+    // Human readable lines are never JSON objects, so JSON messages are recognized without knowing the mode.
+    #[allow(deprecated)]
+    pub(crate) fn parse_json_stdout(&self, stdout: &str) -> Option<TerraformEvent> {
+        if !stdout.starts_with('{') {
+            return None;
+        }
+
+        let message: JsonMessage = serde_json::from_str(stdout).ok()?;
+        let status = match message.kind.as_str() {
+            "apply_start" => Some(TerraformResourceStatus::Started),
+            "apply_progress" => Some(TerraformResourceStatus::InProgress),
+            "apply_complete" => Some(TerraformResourceStatus::Done),
+            "apply_errored" => Some(TerraformResourceStatus::Failed),
+            _ => None,
+        };
+        // planned output changes (with an action) come first, then the applied values
+        let applied_outputs = message.kind == "outputs"
+            && message
+                .outputs
+                .as_ref()
+                .is_some_and(|outputs| outputs.values().all(|output| output.action.is_none()));
+
+        if let Some(changes) = message.changes.as_ref().filter(|_| message.kind == "change_summary") {
+            Some(TerraformEvent {
+                status: Some(TerraformResourceStatus::Completed),
+                create_count: Some(changes.add),
+                update_count: Some(changes.change),
+                delete_count: Some(changes.remove),
+                import_count: Some(changes.import),
+                message: Some(message.message),
+                timestamp: message.timestamp,
+                level: message.level,
+                matched: true,
+                ..TerraformEvent::default()
+            })
+        } else if let (true, Some(change)) = (message.kind == "planned_change", message.change) {
+            Some(TerraformEvent {
+                change: self.json_action_to_change(change.action.as_str()),
+                status: Some(TerraformResourceStatus::Planned),
+                resource_path: Some(change.resource.addr),
+                message: Some(message.message),
+                timestamp: message.timestamp,
+                level: message.level,
+                matched: true,
+                ..TerraformEvent::default()
+            })
+        } else if let (Some(status), Some(hook)) = (status, message.hook) {
+            let attributes = match (&hook.id_key, &hook.id_value) {
+                (Some(key), Some(value)) => vec![(key.clone(), value.clone())].into_iter().collect(),
+                _ => HashMap::new(),
+            };
+
+            Some(TerraformEvent {
+                change: self.json_action_to_change(hook.action.as_str()),
+                status: Some(status),
+                resource_path: Some(hook.resource.addr),
+                id_key: hook.id_key,
+                id_value: hook.id_value,
+                attributes,
+                elapsed: hook.elapsed_seconds.map(Duration::from_secs),
+                message: Some(message.message),
+                timestamp: message.timestamp,
+                level: message.level,
+                matched: true,
+                ..TerraformEvent::default()
+            })
+        } else if let (true, Some(outputs)) = (applied_outputs, message.outputs) {
+            // rendered as the human readable "Outputs:" section would be, values other than strings as JSON
+            Some(TerraformEvent {
+                outputs: outputs
+                    .into_iter()
+                    .map(|(name, output)| {
+                        let value = match output.value {
+                            Some(serde_json::Value::String(value)) => value,
+                            Some(value) => value.to_string(),
+                            None if output.sensitive => String::from("<sensitive>"),
+                            None => String::new(),
+                        };
+                        (name, value)
+                    })
+                    .collect(),
+                message: Some(message.message),
+                timestamp: message.timestamp,
+                level: message.level,
+                matched: true,
+                ..TerraformEvent::default()
+            })
+        } else {
+            Some(TerraformEvent {
+                message: Some(message.message),
+                timestamp: message.timestamp,
+                level: message.level,
+                ..TerraformEvent::default()
+            })
+        }
+    }
+
+    pub(crate) fn parse_diagnostic(&self, stdout: &str) -> Option<TerraformDiagnostic> {
+        if !stdout.starts_with('{') {
+            return None;
+        }
+
+        serde_json::from_str::<JsonMessage>(stdout)
+            .ok()
+            .filter(|message| message.kind == "diagnostic")?
+            .diagnostic
+    }
+
+    // Checked before parsing, so the other lines of a plan can be skipped cheaply.
+    pub(crate) fn is_plan_summary(&self, stdout: &str) -> bool {
+        if stdout.starts_with('{') {
+            serde_json::from_str::<JsonMessage>(stdout)
+                .map(|message| message.kind == "change_summary")
+                .unwrap_or(false)
+        } else {
+            self.plan_completed_regex.is_match(stdout) || self.plan_no_changes_regex.is_match(stdout)
+        }
+    }
+
+    // The targeting warning, as a human readable line or as the "@message" of a JSON one.
+    pub(crate) fn is_partial_plan_notice(&self, stdout: &str) -> bool {
+        if stdout.starts_with('{') {
+            serde_json::from_str::<JsonMessage>(stdout)
+                .map(|message| self.partial_plan_regex.is_match(message.message.as_str()))
+                .unwrap_or(false)
+        } else {
+            self.partial_plan_regex.is_match(stdout)
+        }
+    }
+
+    // Plan and apply both start by refreshing the state, which may take long with large states.
+    #[allow(deprecated)]
+    pub(crate) fn parse_refreshing(&self, stdout: &str) -> Option<TerraformEvent> {
+        let captures = self.refreshing_regex.captures(stdout)?;
+        let (address, id_key, id_value) = self.parse_context_captures(&captures);
+
+        Some(TerraformEvent {
+            status: Some(TerraformResourceStatus::Refreshing),
+            resource_path: address,
+            id_key,
+            id_value,
+            attributes: self.parse_attributes_captures(&captures).into_iter().collect(),
+            matched: true,
+            ..TerraformEvent::default()
+        })
+    }
+
+    pub(crate) fn parse_lock_status(&self, stdout: &str) -> Option<TerraformResourceStatus> {
+        let captures = self.state_lock_regex.captures(stdout)?;
+
+        match captures.name("action").map(|m| m.as_str()) {
+            Some("Releasing") => Some(TerraformResourceStatus::ReleasingLock),
+            _ => Some(TerraformResourceStatus::AcquiringLock),
+        }
+    }
+
+    pub(crate) fn parse_apply_stderr(&self, stderr: String, error: &mut Option<String>) -> TerraformEvent {
+        if let Some(captures) = self.error_regex.captures(stderr.as_str()) {
+            *error = captures.name("message").map(|m| String::from(m.as_str().trim()));
+
+            TerraformEvent {
+                message: error.clone(),
+                remediation: error.as_deref().and_then(remediation),
+                source: stderr,
+                source_stream: TerraformSourceStream::Stderr,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else if let Some(captures) = self
+            .error_address_regex
+            .captures(stderr.as_str())
+            .filter(|_| error.is_some())
+        {
+            let (address, _, _) = self.parse_context_captures(&captures);
+
+            TerraformEvent {
+                status: Some(TerraformResourceStatus::Failed),
+                resource_path: address,
+                message: error.take(),
+                source: stderr,
+                source_stream: TerraformSourceStream::Stderr,
+                matched: true,
+                ..TerraformEvent::default()
+            }
+        } else {
+            self.parse_stderr(stderr)
+        }
+    }
+
+    // Collects the "Outputs:" section printed after "Apply complete!": each output starts with "(name) = (value)",
+    // following lines (multi-line lists, objects and heredocs) are appended to the current value.
+    pub(crate) fn collect_output(&self, stdout: &str, outputs: &mut Option<Vec<(String, String)>>) {
+        let outputs = match outputs {
+            Some(outputs) => outputs,
+            None => {
+                if stdout == "Outputs:" {
+                    *outputs = Some(Vec::new());
+                }
+                return;
+            }
+        };
+
+        let in_heredoc = outputs
+            .last()
+            .and_then(|(_, value)| {
+                let marker = value.strip_prefix("<<")?.lines().next()?.trim_start_matches('-');
+                Some(value.lines().count() == 1 || value.lines().last() != Some(marker))
+            })
+            .unwrap_or(false);
+
+        match self.output_regex.captures(stdout).filter(|_| !in_heredoc) {
+            Some(captures) => outputs.push((String::from(&captures["name"]), String::from(&captures["value"]))),
+            None => {
+                if let Some((_, value)) = outputs.last_mut().filter(|_| !stdout.is_empty() || in_heredoc) {
+                    value.push('\n');
+                    value.push_str(stdout);
+                }
+            }
+        }
+    }
+
+    // Single line strings are unquoted, other values are kept as printed by Terraform.
+    pub(crate) fn unquote_output(value: String) -> String {
+        if value.starts_with('"') && !value.contains('\n') {
+            serde_json::from_str(&value).unwrap_or(value)
+        } else {
+            value
+        }
+    }
+
+    // Line based scan of the lock file as written by `terraform init`, not an HCL parser.
+    pub(crate) fn parse_lock_file(&self, content: &str) -> HashMap<String, String> {
+        let mut providers = HashMap::new();
+        let mut source: Option<&str> = None;
+
+        for line in content.lines() {
+            if let Some(captures) = self.locked_provider_regex.captures(line) {
+                source = captures.name("source").map(|m| m.as_str());
+            } else if line.starts_with('}') {
+                source = None;
+            } else if let (Some(provider), Some(captures)) = (source, self.locked_version_regex.captures(line)) {
+                if let Some(version) = captures.name("version") {
+                    providers.insert(String::from(provider), String::from(version.as_str()));
+                }
+            }
+        }
+
+        providers
+    }
+
+    pub(crate) fn parse_graph_dependencies(&self, dot: &str) -> Option<HashMap<String, Vec<String>>> {
+        if !dot.trim_start().starts_with("digraph") {
+            return None;
+        }
+
+        let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
+        for captures in dot.lines().filter_map(|line| self.graph_edge_regex.captures(line)) {
+            if let (Some(from), Some(to)) = (captures.name("from"), captures.name("to")) {
+                let to = to.as_str().replace("\\\"", "\"");
+                let edges = dependencies.entry(from.as_str().replace("\\\"", "\"")).or_default();
+                if !edges.contains(&to) {
+                    edges.push(to);
+                }
+            }
+        }
+
+        Some(dependencies)
+    }
+
+    pub(crate) fn parse_test_report(&self, context: &ProcessContext) -> TestReport {
+        let mut failures: HashMap<(String, String), String> = HashMap::new();
+        let mut error: Option<String> = None;
+
+        for line in &context.stderr {
+            if let Some(captures) = self.error_regex.captures(line.as_str()) {
+                error = captures.name("message").map(|m| String::from(m.as_str().trim()));
+            } else if let Some(captures) = self.test_error_run_regex.captures(line.as_str()) {
+                if let (Some(file), Some(name), Some(message)) =
+                    (captures.name("file"), captures.name("name"), error.take())
+                {
+                    failures.insert((String::from(file.as_str()), String::from(name.as_str())), message);
+                }
+            }
+        }
+
+        let mut report = TestReport {
+            exit_code: context.exit_code,
+            ..TestReport::default()
+        };
+        let mut file = String::new();
+
+        for line in &context.stdout {
+            if let Some(captures) = self.test_run_regex.captures(line.as_str()) {
+                let name = captures.name("name").map(|m| m.as_str()).unwrap_or_default();
+                let result = captures.name("result").map(|m| m.as_str()).unwrap_or_default();
+                let address = format!("{}/{}", file, name);
+
+                match result {
+                    "pass" => report.passed.push(address),
+                    "fail" | "error" => {
+                        let message = failures
+                            .remove(&(file.clone(), String::from(name)))
+                            .unwrap_or_else(|| String::from(result));
+                        report.failed.push((address, message));
+                    }
+                    _ => {}
+                }
+            } else if let Some(current_file) = line.strip_suffix("... in progress") {
+                file = String::from(current_file);
+            }
+        }
+
+        report
+    }
+
+    pub(crate) fn parse_stats_captures(
+        &self,
+        captures: &regex::Captures,
+    ) -> (Option<u32>, Option<u32>, Option<u32>, Option<u32>) {
+        (
+            captures
+                .name("add_count")
+                .and_then(|m| String::from(m.as_str().trim()).parse::<u32>().ok()),
+            captures
+                .name("change_count")
+                .and_then(|m| String::from(m.as_str().trim()).parse::<u32>().ok()),
+            captures
+                .name("destroy_count")
+                .and_then(|m| String::from(m.as_str().trim()).parse::<u32>().ok()),
+            captures
+                .name("import_count")
+                .and_then(|m| String::from(m.as_str().trim()).parse::<u32>().ok()),
+        )
+    }
+
+    pub(crate) fn parse_context_captures(
+        &self,
+        captures: &regex::Captures,
+    ) -> (Option<String>, Option<String>, Option<String>) {
+        let (id_key, id_value) = self.parse_attributes_captures(captures).into_iter().next().unzip();
+
+        (
+            captures.name("address").map(|m| String::from(m.as_str().trim())),
+            id_key,
+            id_value,
+        )
+    }
+
+    pub(crate) fn parse_attributes_captures(&self, captures: &regex::Captures) -> Vec<(String, String)> {
+        let mut attributes: Vec<(String, String)> = Vec::new();
+
+        if let Some(m) = captures.name("attributes") {
+            for part in m.as_str().split(", ") {
+                match part.split_once('=') {
+                    Some((key, value)) => attributes.push((String::from(key.trim()), String::from(value.trim()))),
+                    // a ", " inside a value, not a new pair
+                    None => {
+                        if let Some((_, value)) = attributes.last_mut() {
+                            value.push_str(", ");
+                            value.push_str(part);
+                        }
+                    }
+                }
+            }
+        }
+
+        attributes
+    }
+
+    pub(crate) fn captures_to_change(&self, captures: regex::Captures) -> Vec<TerraformResourceChange> {
+        if let Some(change) = captures.name("action") {
+            self.action_to_change(change.as_str())
+        } else if captures.name("action_create").is_some() {
+            vec![TerraformResourceChange::Create]
+        } else if captures.name("action_read").is_some() {
+            vec![TerraformResourceChange::Read]
+        } else if captures.name("action_update").is_some() {
+            vec![TerraformResourceChange::Update]
+        } else if captures.name("action_destroy").is_some() {
+            vec![TerraformResourceChange::Destroy]
+        } else if captures.name("action_replace").is_some() {
+            vec![TerraformResourceChange::Destroy, TerraformResourceChange::Create]
+        } else {
+            Vec::new()
+        }
+    }
+
+    // Actions of the JSON UI planned changes ("create", "update", "delete", ...).
+    pub(crate) fn json_action_to_change(&self, action: &str) -> Vec<TerraformResourceChange> {
+        match action {
+            "create" => vec![TerraformResourceChange::Create],
+            "read" => vec![TerraformResourceChange::Read],
+            "update" => vec![TerraformResourceChange::Update],
+            "delete" => vec![TerraformResourceChange::Destroy],
+            "replace" => vec![TerraformResourceChange::Destroy, TerraformResourceChange::Create],
+            _ => Vec::new(),
+        }
+    }
+
+    pub(crate) fn action_to_change(&self, action: &str) -> Vec<TerraformResourceChange> {
+        match action.trim() {
+            "Creating" | "creating" | "Creation" => vec![TerraformResourceChange::Create],
+            "Reading" | "reading" | "Read" => vec![TerraformResourceChange::Read],
+            "Modifying" | "modifying" | "Modifications" => vec![TerraformResourceChange::Update],
+            "Destroying" | "destroying" | "Destruction" => vec![TerraformResourceChange::Destroy],
+            _ => Vec::new(),
+        }
+    }
+}
+
+// Errors with a known fix, from their "Error: (message)" line.
+fn remediation(message: &str) -> Option<TerraformRemediation> {
+    match message.trim() {
+        "Inconsistent dependency lock file" => Some(TerraformRemediation::InitUpgrade),
+        _ => None,
+    }
+}
+
+// Terraform prints nested module names with dots: "a.b" is "module.a.module.b".
+fn module_address(name: &str) -> String {
+    name.split('.')
+        .map(|name| format!("module.{}", name))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// Terraform prints elapsed times as Go durations truncated to the second: "0s", "10s", "1m20s", "1h0m5s".
+fn parse_elapsed(elapsed: &str) -> Option<Duration> {
+    let mut seconds = 0;
+    let mut value = String::new();
+
+    for c in elapsed.chars() {
+        match c {
+            '0'..='9' => value.push(c),
+            'h' | 'm' | 's' => {
+                let unit = match c {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1,
+                };
+                seconds += value.parse::<u64>().ok()? * unit;
+                value.clear();
+            }
+            _ => return None,
+        }
+    }
+
+    if value.is_empty() {
+        Some(Duration::from_secs(seconds))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn post_apply_lines_report_the_address_without_generation() {
+        let parser = Parser::new().unwrap();
+        let cases = [
+            ("Creation", TerraformResourceChange::Create),
+            ("Modifications", TerraformResourceChange::Update),
+            ("Destruction", TerraformResourceChange::Destroy),
+            ("Read", TerraformResourceChange::Read),
+        ];
+
+        for (action, change) in cases.iter() {
+            for suffix in ["", " (deposed object abc123)", " (new resource)"].iter() {
+                let line = format!(
+                    "aws_instance.x[\"a\"]{}: {} complete after 1m2s [id=i-123]",
+                    suffix, action
+                );
+                let event = parser.parse_apply_stdout(line.clone());
+
+                assert_eq!(event.status, Some(TerraformResourceStatus::Done), "{}", line);
+                assert_eq!(
+                    event.resource_path.as_deref(),
+                    Some("aws_instance.x[\"a\"]"),
+                    "{}",
+                    line
+                );
+                assert_eq!(event.change, vec![*change], "{}", line);
+                assert_eq!(event.elapsed, Some(Duration::from_secs(62)), "{}", line);
+                assert_eq!(
+                    event.attributes.get("id").map(String::as_str),
+                    Some("i-123"),
+                    "{}",
+                    line
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn still_destroying_lines_report_increasing_elapsed_times() {
+        let parser = Parser::new().unwrap();
+        let lines = [
+            "aws_db_instance.main: Still destroying... [id=db-ABC123, 10s elapsed]",
+            "aws_db_instance.main: Still destroying... [id=db-ABC123, 5m0s elapsed]",
+            "aws_db_instance.main: Still destroying... [id=db-ABC123, 10m10s elapsed]",
+            "aws_db_instance.main: Still destroying... [12m20s elapsed]",
+        ];
+
+        let mut previous = None;
+        for line in lines.iter() {
+            let event = parser.parse_apply_stdout(String::from(*line));
+
+            assert_eq!(event.status, Some(TerraformResourceStatus::InProgress), "{}", line);
+            assert_eq!(event.change, vec![TerraformResourceChange::Destroy], "{}", line);
+            assert_eq!(event.resource_path.as_deref(), Some("aws_db_instance.main"), "{}", line);
+
+            let elapsed = event.elapsed.expect(line);
+            assert!(previous.is_none_or(|previous| elapsed > previous), "{}", line);
+            previous = Some(elapsed);
+        }
+        assert_eq!(previous, Some(Duration::from_secs(12 * 60 + 20)));
+    }
+
+    #[test]
+    fn replacement_wordings_are_destructive() {
+        let parser = Parser::new().unwrap();
+        let lines = [
+            "  # aws_instance.x must be replaced",
+            "  # aws_instance.x is tainted, so must be replaced",
+            "  # aws_instance.x will be replaced, as requested",
+            "  # aws_instance.x will be replaced due to changes in replace_triggered_by",
+        ];
+
+        for line in lines.iter() {
+            let event = parser.parse_plan_stdout(String::from(*line));
+
+            assert_eq!(event.status, Some(TerraformResourceStatus::Planned), "{}", line);
+            assert_eq!(event.resource_path.as_deref(), Some("aws_instance.x"), "{}", line);
+            assert_eq!(
+                event.change,
+                vec![TerraformResourceChange::Destroy, TerraformResourceChange::Create],
+                "{}",
+                line
+            );
+            assert!(event.is_destructive(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn no_changes_counts_match_the_json_summary() {
+        let parser = Parser::new().unwrap();
+        let human = parser.parse_plan_stdout(String::from(
+            "No changes. Your infrastructure matches the configuration.",
+        ));
+        let json = parser.parse_plan_stdout(String::from(
+            r#"{"@message":"Plan: 0 to add, 0 to change, 0 to destroy.","type":"change_summary","changes":{"add":0,"change":0,"remove":0,"import":0,"operation":"plan"}}"#,
+        ));
+
+        for event in [&human, &json].iter() {
+            assert_eq!(event.status, Some(TerraformResourceStatus::Completed));
+            assert_eq!(
+                (
+                    event.create_count,
+                    event.update_count,
+                    event.delete_count,
+                    event.import_count
+                ),
+                (Some(0), Some(0), Some(0), Some(0))
+            );
+        }
+    }
+
+    #[test]
+    fn json_apply_messages_report_resource_progress_and_outputs() {
+        let parser = Parser::new().unwrap();
+        let lines = [
+            r#"{"@level":"info","@message":"terraform_data.a: Creating...","@timestamp":"2026-10-16T13:13:25.000000Z","hook":{"resource":{"addr":"terraform_data.a"},"action":"create"},"type":"apply_start"}"#,
+            r#"{"@level":"info","@message":"terraform_data.a: Still creating... [10s elapsed]","@timestamp":"2026-10-16T13:13:35.000000Z","hook":{"resource":{"addr":"terraform_data.a"},"action":"create","elapsed_seconds":10},"type":"apply_progress"}"#,
+            r#"{"@level":"info","@message":"terraform_data.a: Creation complete after 12s [id=abc]","@timestamp":"2026-10-16T13:13:37.000000Z","hook":{"resource":{"addr":"terraform_data.a"},"action":"create","id_key":"id","id_value":"abc","elapsed_seconds":12},"type":"apply_complete"}"#,
+            r#"{"@level":"info","@message":"terraform_data.b: Creation errored after 1s","@timestamp":"2026-10-16T13:13:38.000000Z","hook":{"resource":{"addr":"terraform_data.b"},"action":"create","elapsed_seconds":1},"type":"apply_errored"}"#,
+        ];
+        let events: Vec<TerraformEvent> = lines
+            .iter()
+            .map(|line| parser.parse_apply_stdout(String::from(*line)))
+            .collect();
+
+        let statuses: Vec<Option<TerraformResourceStatus>> = events.iter().map(|event| event.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Some(TerraformResourceStatus::Started),
+                Some(TerraformResourceStatus::InProgress),
+                Some(TerraformResourceStatus::Done),
+                Some(TerraformResourceStatus::Failed),
+            ]
+        );
+        assert_eq!(events[0].resource_path.as_deref(), Some("terraform_data.a"));
+        assert_eq!(events[0].change, vec![TerraformResourceChange::Create]);
+        assert_eq!(events[2].elapsed, Some(Duration::from_secs(12)));
+        assert_eq!(events[2].attributes.get("id").map(String::as_str), Some("abc"));
+        assert_eq!(events[3].resource_path.as_deref(), Some("terraform_data.b"));
+
+        let planned = parser.parse_apply_stdout(String::from(
+            r#"{"@message":"Outputs: 2","outputs":{"list":{"sensitive":false,"action":"noop"},"secret":{"sensitive":true,"action":"noop"}},"type":"outputs"}"#,
+        ));
+        assert!(planned.outputs.is_empty());
+
+        let applied = parser.parse_apply_stdout(String::from(
+            r#"{"@message":"Outputs: 3","outputs":{"list":{"sensitive":false,"type":["tuple",["string","string"]],"value":["a","b"]},"name":{"sensitive":false,"type":"string","value":"x"},"secret":{"sensitive":true,"type":"string"}},"type":"outputs"}"#,
+        ));
+        assert_eq!(applied.outputs.get("list").map(String::as_str), Some(r#"["a","b"]"#));
+        assert_eq!(applied.outputs.get("name").map(String::as_str), Some("x"));
+        assert_eq!(applied.outputs.get("secret").map(String::as_str), Some("<sensitive>"));
+    }
+}
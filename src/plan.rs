@@ -0,0 +1,128 @@
+use crate::errors::Error;
+use crate::event::{TerraformEvent, TerraformResourceChange, TerraformResourceStatus};
+use crate::json::JsonPlan;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub struct PlanFile {
+    path: PathBuf,
+}
+
+impl PlanFile {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for PlanFile {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PlanFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanFileInfo {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub modified: Option<SystemTime>,
+}
+
+// Figures for guardrails on a plan (artifact size limits, blast radius), from its file and its summary line.
+#[derive(Debug, Clone)]
+pub struct PlanSummary {
+    pub file: PlanFileInfo,
+    pub create_count: u32,
+    pub update_count: u32,
+    pub delete_count: u32,
+    pub import_count: u32,
+    // Resources with planned changes (data sources reads excluded), a replacement counting once (it is both a create
+    // and a delete above).
+    pub resource_changes: usize,
+}
+
+// A plan, as described by `terraform show -json`.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub resource_changes: Vec<ResourceChange>,
+}
+
+// `actions` is empty for resources without changes, and a replacement is a single `Replace` action.
+#[derive(Debug, Clone)]
+pub struct ResourceChange {
+    pub address: String,
+    pub actions: Vec<TerraformResourceChange>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl Plan {
+    // Reads a `terraform show -json` plan document.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str::<JsonPlan>(json)?.into())
+    }
+
+    // The events a live plan with these changes sends: a `Planned` event per changed resource, then the `Completed`
+    // summary. Replacements are a `Destroy` and a `Create` change, as in the human readable output.
+    pub fn events(&self) -> Vec<TerraformEvent> {
+        let mut create_count = 0;
+        let mut update_count = 0;
+        let mut delete_count = 0;
+
+        let mut events: Vec<TerraformEvent> = self
+            .resource_changes
+            .iter()
+            .filter(|resource_change| !resource_change.actions.is_empty())
+            .map(|resource_change| {
+                let change = match resource_change.actions.as_slice() {
+                    [TerraformResourceChange::Replace] => {
+                        vec![TerraformResourceChange::Destroy, TerraformResourceChange::Create]
+                    }
+                    actions => actions.to_vec(),
+                };
+                for action in &change {
+                    match action {
+                        TerraformResourceChange::Create => create_count += 1,
+                        TerraformResourceChange::Update => update_count += 1,
+                        TerraformResourceChange::Destroy => delete_count += 1,
+                        _ => {}
+                    }
+                }
+
+                TerraformEvent {
+                    change,
+                    status: Some(TerraformResourceStatus::Planned),
+                    resource_path: Some(resource_change.address.clone()),
+                    command: String::from("plan"),
+                    matched: true,
+                    ..TerraformEvent::default()
+                }
+                .with_parsed_address()
+            })
+            .collect();
+
+        events.push(TerraformEvent {
+            status: Some(TerraformResourceStatus::Completed),
+            create_count: Some(create_count),
+            update_count: Some(update_count),
+            delete_count: Some(delete_count),
+            command: String::from("plan"),
+            matched: true,
+            ..TerraformEvent::default()
+        });
+
+        events
+    }
+}
@@ -0,0 +1,92 @@
+use crate::errors::Error;
+use crate::event::TerraformEvent;
+use crate::process::ProcessContext;
+use crate::sink::EventSink;
+use crate::Terraform;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::thread;
+
+// Runs the same operation over several independent working directories (stacks), at most `concurrency` at a
+// time. Every added `Terraform` sends its events to the pool sender, tagged with its stack identifier.
+pub struct TerraformPool<P, Q, S = Sender<TerraformEvent>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    S: EventSink,
+{
+    stacks: Vec<(String, Terraform<P, Q, S>)>,
+    concurrency: usize,
+    sender: S,
+}
+
+impl<P, Q, S> TerraformPool<P, Q, S>
+where
+    P: AsRef<Path> + Sync,
+    Q: AsRef<Path> + Sync,
+    S: EventSink + Clone + Sync,
+{
+    pub fn new(concurrency: usize, sender: S) -> Self {
+        Self {
+            stacks: Vec::new(),
+            concurrency: concurrency.max(1),
+            sender,
+        }
+    }
+
+    // Replaces the sender of `terraform` with the pool one.
+    pub fn add(&mut self, stack: &str, mut terraform: Terraform<P, Q, S>) {
+        terraform.sender = self.sender.clone();
+        terraform.set_stack(Some(String::from(stack)));
+        self.stacks.push((String::from(stack), terraform));
+    }
+
+    pub fn stacks(&self) -> impl Iterator<Item = &str> {
+        self.stacks.iter().map(|(stack, _)| stack.as_str())
+    }
+
+    pub fn get(&self, stack: &str) -> Option<&Terraform<P, Q, S>> {
+        self.stacks
+            .iter()
+            .find(|(name, _)| name == stack)
+            .map(|(_, terraform)| terraform)
+    }
+
+    // Calls `operation` once per stack (e.g. `|terraform| terraform.run_apply("plan.tfplan")`) and returns the
+    // results in the order the stacks were added. A failing stack doesn't stop the other ones.
+    pub fn run<F>(&self, operation: F) -> Vec<(String, Result<ProcessContext, Error>)>
+    where
+        F: Fn(&Terraform<P, Q, S>) -> Result<ProcessContext, Error> + Sync,
+    {
+        let next = AtomicUsize::new(0);
+        let results: Vec<Mutex<Option<Result<ProcessContext, Error>>>> =
+            self.stacks.iter().map(|_| Mutex::new(None)).collect();
+
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(self.stacks.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some((_, terraform)) = self.stacks.get(index) else {
+                        break;
+                    };
+
+                    let result = operation(terraform);
+                    if let Ok(mut slot) = results[index].lock() {
+                        *slot = Some(result);
+                    }
+                });
+            }
+        });
+
+        self.stacks
+            .iter()
+            .zip(results)
+            .filter_map(|((stack, _), result)| {
+                let result = result.into_inner().ok().flatten()?;
+                Some((stack.clone(), result))
+            })
+            .collect()
+    }
+}
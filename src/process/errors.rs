@@ -1,11 +1,16 @@
 #[derive(Debug)]
 pub enum Error {
     TimeoutError,
-    IOError(String),
+    IdleTimeout,
+    // The kind of the original error, then its message.
+    IOError(std::io::ErrorKind, String),
+    Interrupted,
+    KilledBySignal(i32),
+    InterruptHandlerError(String),
 }
 
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Error {
-        Error::IOError(e.to_string())
+        Error::IOError(e.kind(), e.to_string())
     }
 }
@@ -0,0 +1,30 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use super::Error;
+
+// Number of Ctrl-C received so far: a command is interrupted by the ones received after it was spawned.
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+// Registers a SIGINT (Ctrl-C) handler: running commands are then interrupted gracefully, and their `wait` returns
+// `Error::Interrupted`, while commands spawned after a Ctrl-C run normally. Every command spawned once the handler is
+// installed runs in its own process group (see `Process::set_process_group`). This replaces any handler previously
+// installed by the application.
+pub fn install_interrupt_handler() -> Result<(), Error> {
+    ctrlc::set_handler(request).map_err(|e| Error::InterruptHandlerError(e.to_string()))?;
+    INSTALLED.store(true, Ordering::SeqCst);
+
+    Ok(())
+}
+
+pub(crate) fn is_installed() -> bool {
+    INSTALLED.load(Ordering::SeqCst)
+}
+
+pub(crate) fn request() {
+    INTERRUPTS.fetch_add(1, Ordering::SeqCst);
+}
+
+pub(crate) fn count() -> usize {
+    INTERRUPTS.load(Ordering::SeqCst)
+}
@@ -1,17 +1,43 @@
 mod errors;
+#[cfg(feature = "ctrlc")]
+mod interrupt;
+mod registry;
 
 use std::collections::HashMap;
-use std::ffi::OsStr;
-use std::io::{BufRead, BufReader, Read};
+use std::ffi::{OsStr, OsString};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
 use std::path::Path;
-use std::process::{Child, Command, Stdio};
-use std::sync::mpsc::{channel, Sender};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt;
 
+use crate::diagnostic::TerraformDiagnostic;
+use crate::event::TerraformEvent;
+use crate::outcome::RunOutcome;
+use crate::parser::Parser;
+
 pub use errors::Error;
+#[cfg(feature = "ctrlc")]
+pub use interrupt::install_interrupt_handler;
+pub(crate) use registry::Cancellation;
+pub use registry::ProcessRegistry;
+
+type LogSink = Arc<Mutex<Box<dyn Write + Send>>>;
+
+// Same as `BufReader`'s default.
+const DEFAULT_BUFFER_CAPACITY: usize = 8 * 1024;
+
+// `wait` polls tightly while lines arrive, then backs off (doubling) up to the maximum while the command is quiet,
+// e.g. during a long resource creation.
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(20);
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub type CommandConfigurator = Box<dyn Fn(&mut Command) + Send + Sync>;
+pub type MetricsCallback = Box<dyn Fn(&str, Duration, Option<i32>) + Send + Sync>;
 
 pub struct Process<P, Q>
 where
@@ -21,7 +47,19 @@ where
     binary_path: P,
     working_directory: Q,
     envs: HashMap<String, String>,
+    clear_env: bool,
+    use_chdir: bool,
+    inherit_output: bool,
+    process_group: bool,
     timeout: Duration,
+    idle_timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    log_sink: Option<LogSink>,
+    configure_command: Option<CommandConfigurator>,
+    metrics_callback: Option<Arc<MetricsCallback>>,
+    registry: Option<ProcessRegistry>,
+    buffer_capacity: usize,
+    max_line_length: Option<usize>,
 }
 
 impl<P, Q> Process<P, Q>
@@ -34,82 +72,428 @@ where
             binary_path,
             working_directory,
             envs,
+            clear_env: false,
+            use_chdir: false,
+            inherit_output: false,
+            process_group: false,
             timeout,
+            idle_timeout: None,
+            deadline: None,
+            log_sink: None,
+            configure_command: None,
+            metrics_callback: None,
+            registry: None,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            max_line_length: None,
         }
     }
 
+    // Every stdout/stderr line of every spawned command is also written to this sink, as it is received.
+    pub fn set_log_sink(&mut self, sink: Box<dyn Write + Send>) {
+        self.log_sink = Some(Arc::new(Mutex::new(sink)));
+    }
+
+    // An absolute deadline shared by every spawned command, on top of the per-command timeout.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    // Fails a command with `Error::IdleTimeout` when it doesn't output any line for this long.
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    // Capacity of the buffers reading stdout and stderr; larger buffers mean fewer reads for verbose commands.
+    pub fn set_buffer_capacity(&mut self, buffer_capacity: usize) {
+        self.buffer_capacity = buffer_capacity;
+    }
+
+    // Lines longer than this (in bytes) are truncated while read, so a provider dumping a huge document on a
+    // single line doesn't have to be held in memory. Unlimited by default.
+    pub fn set_max_line_length(&mut self, max_line_length: Option<usize>) {
+        self.max_line_length = max_line_length;
+    }
+
+    // Adds (or replaces) a variable of the environment passed to every command.
+    pub fn set_env(&mut self, name: &str, value: &str) {
+        self.envs.insert(String::from(name), String::from(value));
+    }
+
+    // When set, the child only sees the variables from `envs` instead of inheriting the current environment.
+    pub fn set_clear_env(&mut self, clear_env: bool) {
+        self.clear_env = clear_env;
+    }
+
+    // Passes the working directory with Terraform's `-chdir` global option instead of changing the process directory.
+    pub fn set_use_chdir(&mut self, use_chdir: bool) {
+        self.use_chdir = use_chdir;
+    }
+
+    // Lets commands write to the standard output and error of the current process (e.g. the terminal, in color if
+    // `CommonFlags::no_color` is disabled) instead of piping them: no event is produced and nothing is captured, only
+    // the exit code. The idle timeout doesn't apply then, and approval prompts can't be answered: apply and destroy
+    // fail with `Error::ApprovalUnsupported` unless approval is automatic.
+    pub fn set_inherit_output(&mut self, inherit_output: bool) {
+        self.inherit_output = inherit_output;
+    }
+
+    // Unix only: runs every command in its own process group, so provider plugins are killed along with Terraform on
+    // timeout. The terminal's Ctrl-C then doesn't reach Terraform anymore: stop it with `install_interrupt_handler`
+    // or a `ProcessRegistry`, or it would outlive the current process. Always on once the interrupt handler is
+    // installed, as it forwards Ctrl-C itself.
+    pub fn set_process_group(&mut self, process_group: bool) {
+        self.process_group = process_group;
+    }
+
+    // Called on every command once it is fully set up, right before it is spawned.
+    pub fn set_configure_command(&mut self, configure_command: Option<CommandConfigurator>) {
+        self.configure_command = configure_command;
+    }
+
+    // Called once every command is over with its name ("plan", "apply", ...), the time elapsed since it was spawned,
+    // and its exit code (`None` when it was killed on timeout or by a signal).
+    pub fn set_metrics_callback(&mut self, metrics_callback: Option<MetricsCallback>) {
+        self.metrics_callback = metrics_callback.map(Arc::new);
+    }
+
+    // Every spawned command is registered there until its context is dropped, see `ProcessRegistry::cancel_all`.
+    pub fn set_registry(&mut self, registry: Option<ProcessRegistry>) {
+        self.registry = registry;
+    }
+
+    pub fn inherit_output(&self) -> bool {
+        self.inherit_output
+    }
+
+    pub fn binary_path(&self) -> &Path {
+        self.binary_path.as_ref()
+    }
+
+    pub fn working_directory(&self) -> &Path {
+        self.working_directory.as_ref()
+    }
+
     pub fn spawn<I, S>(&self, args: I) -> Result<ProcessContext, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.spawn_with_envs(args, &HashMap::new())
+    }
+
+    // Same as `spawn`, with `envs` merged over the process environment for this command only (per-call values win).
+    pub fn spawn_with_envs<I, S>(&self, args: I, envs: &HashMap<String, String>) -> Result<ProcessContext, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = self.command(args, envs);
+
+        let mut context = ProcessContext::new_with_deadline(&mut command, self.timeout, self.deadline)?;
+        context.log_sink = self.log_sink.clone();
+        context.metrics_callback = self.metrics_callback.clone();
+        context.cancelled = self.register();
+        context.idle_timeout = self.idle_timeout.filter(|_| !self.inherit_output);
+        context.buffer_capacity = self.buffer_capacity;
+        context.max_line_length = self.max_line_length;
+
+        Ok(context)
+    }
+
+    // What `ProcessContext` does with its lines and exit code, for the commands run without it (the async streams).
+    #[cfg(feature = "tokio")]
+    pub(crate) fn log_line(&self, line: &str) {
+        if let Some(Ok(mut sink)) = self.log_sink.as_ref().map(|sink| sink.lock()) {
+            let _ = writeln!(sink, "{}", line);
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn report_metrics(&self, command: &str, elapsed: Duration, exit_code: Option<i32>) {
+        if let Some(metrics_callback) = &self.metrics_callback {
+            metrics_callback(command, elapsed, exit_code);
+        }
+    }
+
+    pub(crate) fn register(&self) -> Option<Arc<Cancellation>> {
+        self.registry.as_ref().map(ProcessRegistry::register)
+    }
+
+    // The command as spawned, with its environment, working directory and standard streams.
+    pub(crate) fn command<I, S>(&self, args: I, envs: &HashMap<String, String>) -> Command
     where
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr>,
     {
         let mut command = Command::new(self.binary_path.as_ref());
-        let command = command
-            .current_dir(self.working_directory.as_ref())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
+        if self.clear_env {
+            command.env_clear();
+        }
+
+        // in its own process group, the child and its provider plugins can be killed together on timeout, and it doesn't
+        // receive the terminal's SIGINT, so it is only interrupted once (see `install_interrupt_handler`); unless
+        // something forwards Ctrl-C, it must stay in the terminal's group not to outlive the current process
+        #[cfg(all(unix, feature = "ctrlc"))]
+        let process_group = self.process_group || interrupt::is_installed();
+        #[cfg(all(unix, not(feature = "ctrlc")))]
+        let process_group = self.process_group;
+        #[cfg(unix)]
+        if process_group {
+            std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        }
+
+        if self.use_chdir {
+            let mut chdir_arg = OsString::from("-chdir=");
+            chdir_arg.push(self.working_directory.as_ref());
+            command.arg(chdir_arg);
+        } else {
+            command.current_dir(self.working_directory.as_ref());
+        }
+
+        let output = || {
+            if self.inherit_output {
+                Stdio::inherit()
+            } else {
+                Stdio::piped()
+            }
+        };
+
+        command
+            .stdin(Stdio::piped())
+            .stdout(output())
+            .stderr(output())
             .args(args)
-            .envs(&self.envs);
+            .envs(&self.envs)
+            .envs(envs);
 
-        let context = ProcessContext::new(command, self.timeout)?;
+        if let Some(configure_command) = &self.configure_command {
+            configure_command(&mut command);
+        }
 
-        Ok(context)
+        command
     }
 }
 
 pub struct ProcessContext {
     child: Child,
+    program: OsString,
+    args: Vec<OsString>,
     start: Instant,
     timeout: Duration,
+    deadline: Option<Instant>,
+    idle_timeout: Option<Duration>,
+    last_line: Instant,
+    log_sink: Option<LogSink>,
+    metrics_callback: Option<Arc<MetricsCallback>>,
+    cancelled: Option<Arc<Cancellation>>,
+    // Ctrl-C received before the command was spawned, which don't concern it.
+    #[cfg(feature = "ctrlc")]
+    interrupts: usize,
+    buffer_capacity: usize,
+    max_line_length: Option<usize>,
 
     pub stdout: Vec<String>,
     pub stderr: Vec<String>,
     pub exit_code: Option<i32>,
+    // Lines starting with "Warning: " and "Error: ", counted over both streams.
+    pub warning_count: usize,
+    pub error_count: usize,
+    // Set by plans run with `-target`, whose changes may not cover the whole configuration.
+    pub partial_plan: bool,
+    // The "diagnostic" messages of JSON output (`-json`), in order.
+    pub diagnostics: Vec<TerraformDiagnostic>,
+    // Set by `Terraform` from its exit code policy, once the command exited.
+    pub outcome: Option<RunOutcome>,
     #[cfg(unix)]
     pub signal_code: Option<i32>,
 }
 
 impl ProcessContext {
     pub fn new(command: &mut Command, timeout: Duration) -> Result<Self, Error> {
+        Self::new_with_deadline(command, timeout, None)
+    }
+
+    pub fn new_with_deadline(
+        command: &mut Command,
+        timeout: Duration,
+        deadline: Option<Instant>,
+    ) -> Result<Self, Error> {
         let start = Instant::now();
+        #[cfg(feature = "ctrlc")]
+        let interrupts = interrupt::count();
 
         Ok(Self {
             child: command.spawn()?,
+            program: command.get_program().to_os_string(),
+            args: command.get_args().map(OsStr::to_os_string).collect(),
             start,
             timeout,
+            deadline,
+            idle_timeout: None,
+            last_line: start,
+            log_sink: None,
+            metrics_callback: None,
+            cancelled: None,
+            #[cfg(feature = "ctrlc")]
+            interrupts,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            max_line_length: None,
             stdout: Vec::new(),
             stderr: Vec::new(),
             exit_code: None,
+            warning_count: 0,
+            error_count: 0,
+            partial_plan: false,
+            diagnostics: Vec::new(),
+            outcome: None,
             #[cfg(unix)]
             signal_code: None,
         })
     }
 
+    pub fn program(&self) -> &OsStr {
+        &self.program
+    }
+
+    pub fn args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    // A shell-like rendering of the program and its arguments, arguments with whitespace or quotes being quoted.
+    pub fn command_line(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(self.args.iter())
+            .map(|arg| {
+                let arg = arg.to_string_lossy();
+                if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+                    format!("'{}'", arg.replace('\'', "'\\''"))
+                } else {
+                    arg.into_owned()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Re-parses the captured output as `command` ("init", "plan", "apply", ...) does when streaming events,
+    // for output captured without a `Terraform` instance or stored for later. Stdout lines come first.
+    pub fn events(&self, command: &str) -> Vec<TerraformEvent> {
+        Parser::shared().parse_captured(command, &self.stdout, &self.stderr)
+    }
+
+    pub fn stdout_string(&self) -> String {
+        self.stdout.join("\n")
+    }
+
+    pub fn stderr_string(&self) -> String {
+        self.stderr.join("\n")
+    }
+
+    // The most recent "Error: " diagnostic of stderr with its detail lines, up to the next diagnostic, without the
+    // trailing blank lines.
+    pub fn last_error(&self) -> Option<String> {
+        let start = self.stderr.iter().rposition(|line| line.starts_with("Error: "))?;
+        let block = &self.stderr[start..];
+        let end = block
+            .iter()
+            .skip(1)
+            .position(|line| line.starts_with("Warning: "))
+            .map_or(block.len(), |position| position + 1);
+        let end = block[..end]
+            .iter()
+            .rposition(|line| !line.trim().is_empty())
+            .map_or(1, |position| position + 1);
+
+        Some(block[..end].join("\n"))
+    }
+
+    pub fn take_stdin(&mut self) -> Option<ChildStdin> {
+        self.child.stdin.take()
+    }
+
+    fn is_expired(&self) -> bool {
+        let now = Instant::now();
+
+        now.duration_since(self.start) >= self.timeout || self.deadline.map(|deadline| now >= deadline).unwrap_or(false)
+    }
+
+    // Kills the whole process group when the child leads one (see `Process::command`), so provider plugins aren't left
+    // orphaned; only the child otherwise.
+    fn kill(&mut self) {
+        #[cfg(unix)]
+        let killed =
+            unsafe { libc::killpg(self.child.id() as libc::pid_t, libc::SIGKILL) } == 0 || self.child.kill().is_ok();
+        #[cfg(not(unix))]
+        let killed = self.child.kill().is_ok();
+
+        if killed {
+            let _ = self.child.wait();
+        }
+    }
+
+    // Terraform handles a single SIGINT as a request to stop gracefully (releasing locks, persisting state)
+    fn interrupt(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(self.child.id() as libc::pid_t, libc::SIGINT);
+        }
+        #[cfg(not(unix))]
+        let _ = self.child.kill();
+    }
+
+    // Requested through the registry, or by Ctrl-C once the interrupt handler is installed.
+    fn is_interrupt_requested(&self) -> bool {
+        #[cfg(feature = "ctrlc")]
+        if interrupt::count() != self.interrupts {
+            return true;
+        }
+
+        self.cancelled
+            .as_ref()
+            .map(|cancelled| cancelled.is_cancelled())
+            .unwrap_or(false)
+    }
+
+    fn is_idle(&self) -> bool {
+        self.idle_timeout
+            .map(|idle_timeout| self.last_line.elapsed() >= idle_timeout)
+            .unwrap_or(false)
+    }
+
     pub fn wait<'a, P, Q>(mut self, mut stdout: P, mut stderr: Q) -> Result<Self, Error>
     where
         P: 'a + FnMut(Option<String>),
         Q: 'a + FnMut(Option<String>),
     {
+        // close stdin unless it was taken by the caller, so the child never blocks on it
+        drop(self.child.stdin.take());
+
         let (stdout_tx, stdout_rx) = channel();
-        let stdout_processor = StreamProcessor::new(self.child.stdout.take(), stdout_tx);
+        let stdout_processor = StreamProcessor::new(self.child.stdout.take(), stdout_tx)
+            .with_limits(self.buffer_capacity, self.max_line_length);
 
         let stdout_reader = std::thread::spawn(|| {
             stdout_processor.stream();
         });
 
         let (stderr_tx, stderr_rx) = channel();
-        let stderr_processor = StreamProcessor::new(self.child.stderr.take(), stderr_tx);
+        let stderr_processor = StreamProcessor::new(self.child.stderr.take(), stderr_tx)
+            .with_limits(self.buffer_capacity, self.max_line_length);
 
         let stderr_reader = std::thread::spawn(|| {
             stderr_processor.stream();
         });
 
+        let mut interrupted = false;
+        let mut poll_interval = MIN_POLL_INTERVAL;
+
         loop {
             match self.child.try_wait() {
                 Err(_) => {
-                    let _ = self.child.kill().map(|_| self.child.wait());
+                    self.kill();
                     let _ = stdout_reader.join();
                     let _ = stderr_reader.join();
+                    self.report_metrics();
 
                     return Err(Error::TimeoutError);
                 }
@@ -122,43 +506,113 @@ impl ProcessContext {
 
                     let _ = stdout_reader.join();
                     let _ = stderr_reader.join();
+
+                    self.drain(&stdout_rx, &mut stdout, true);
+                    self.drain(&stderr_rx, &mut stderr, false);
+                    self.report_metrics();
+
+                    if interrupted {
+                        return Err(Error::Interrupted);
+                    }
+
+                    // e.g. the OOM killer: without an exit code, this must not look like a success
+                    #[cfg(unix)]
+                    if let Some(signal) = self.signal_code {
+                        return Err(Error::KilledBySignal(signal));
+                    }
+
                     return Ok(self);
                 }
                 Ok(None) => {
-                    if self.start.elapsed().as_secs() < self.timeout.as_secs() {
-                        std::thread::sleep(std::time::Duration::from_millis(20));
-
-                        while let Ok(line) = stdout_rx.try_recv() {
-                            if let Ok(line) = line {
-                                stdout(Some(line.clone()));
-                                self.stdout.push(line);
-                            } else {
-                                stdout(None);
-                                self.stdout.push(String::from("<error retrieving stream content>"));
-                            }
-                        }
-
-                        while let Ok(line) = stderr_rx.try_recv() {
-                            if let Ok(line) = line {
-                                stderr(Some(line.clone()));
-                                self.stderr.push(line);
-                            } else {
-                                stderr(None);
-                                self.stderr.push(String::from("<error retrieving stream content>"));
-                            }
-                        }
+                    if !interrupted && self.is_interrupt_requested() {
+                        self.interrupt();
+                        interrupted = true;
+                    }
+
+                    let idle = self.is_idle();
+                    if !idle && !self.is_expired() {
+                        std::thread::sleep(poll_interval);
+
+                        let last_line = self.last_line;
+                        self.drain(&stdout_rx, &mut stdout, true);
+                        self.drain(&stderr_rx, &mut stderr, false);
+
+                        poll_interval = if self.last_line == last_line {
+                            (poll_interval * 2).min(MAX_POLL_INTERVAL)
+                        } else {
+                            MIN_POLL_INTERVAL
+                        };
 
                         continue;
                     }
 
-                    let _ = self.child.kill().map(|_| self.child.wait());
+                    self.kill();
                     let _ = stdout_reader.join();
                     let _ = stderr_reader.join();
+                    self.report_metrics();
+
+                    if idle {
+                        return Err(Error::IdleTimeout);
+                    }
                     return Err(Error::TimeoutError);
                 }
             };
         }
     }
+
+    fn report_metrics(&self) {
+        if let Some(metrics_callback) = &self.metrics_callback {
+            // the first argument that isn't a global option (such as `-chdir`)
+            let command = self
+                .args
+                .iter()
+                .map(|arg| arg.to_string_lossy())
+                .find(|arg| !arg.starts_with('-'))
+                .unwrap_or_default();
+
+            metrics_callback(&command, self.start.elapsed(), self.exit_code);
+        }
+    }
+
+    fn drain<F>(&mut self, receiver: &Receiver<Result<String, Error>>, callback: &mut F, is_stdout: bool)
+    where
+        F: FnMut(Option<String>),
+    {
+        let lines = if is_stdout { &mut self.stdout } else { &mut self.stderr };
+
+        while let Ok(line) = receiver.try_recv() {
+            self.last_line = Instant::now();
+
+            if let Ok(line) = line {
+                if let Some(Ok(mut sink)) = self.log_sink.as_ref().map(|sink| sink.lock()) {
+                    let _ = writeln!(sink, "{}", line);
+                }
+
+                if line.starts_with("Warning: ") {
+                    self.warning_count += 1;
+                } else if line.starts_with("Error: ") {
+                    self.error_count += 1;
+                } else if let Some(diagnostic) = Parser::shared().parse_diagnostic(&line) {
+                    self.diagnostics.push(diagnostic);
+                }
+
+                callback(Some(line.clone()));
+                lines.push(line);
+            } else {
+                callback(None);
+                lines.push(String::from("<error retrieving stream content>"));
+            }
+        }
+    }
+}
+
+// A context dropped without waiting for its command (e.g. on an early return) doesn't leave the child running.
+impl Drop for ProcessContext {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            self.kill();
+        }
+    }
 }
 
 pub struct StreamProcessor<T>
@@ -167,6 +621,8 @@ where
 {
     source: Option<T>,
     sender: Sender<Result<String, Error>>,
+    buffer_capacity: usize,
+    max_line_length: Option<usize>,
 }
 
 impl<T> StreamProcessor<T>
@@ -174,15 +630,126 @@ where
     T: Read,
 {
     pub fn new(source: Option<T>, sender: Sender<Result<String, Error>>) -> Self {
-        Self { source, sender }
+        Self {
+            source,
+            sender,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            max_line_length: None,
+        }
+    }
+
+    pub fn with_limits(mut self, buffer_capacity: usize, max_line_length: Option<usize>) -> Self {
+        self.buffer_capacity = buffer_capacity;
+        self.max_line_length = max_line_length;
+        self
     }
 
-    fn stream(self) {
-        if let Some(source) = self.source {
-            for line in BufReader::new(source).lines().enumerate() {
-                let (_, line) = line;
-                let _ = self.sender.send(line.map_err(|e| Error::IOError(e.to_string())));
+    // Lines are split on '\n' (dropping a trailing '\r'), and decoded lossily: invalid UTF-8 doesn't lose the line.
+    // Bytes past `max_line_length` are skipped as they are read, instead of being accumulated. A UTF-8 BOM starting
+    // the stream (as in redirected output on Windows) is dropped, so the first line still matches anchored patterns.
+    fn stream(mut self) {
+        let source = match self.source.take() {
+            Some(source) => source,
+            None => return,
+        };
+
+        let mut reader = BufReader::with_capacity(self.buffer_capacity, source);
+        let mut line = Vec::new();
+        let mut first_line = true;
+
+        loop {
+            let (consumed, end_of_line) = match reader.fill_buf() {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    let _ = self.sender.send(Err(Error::from(e)));
+                    return;
+                }
+                Ok([]) => {
+                    if !line.is_empty() {
+                        self.send_line(&line, first_line);
+                    }
+                    return;
+                }
+                Ok(buffer) => {
+                    let (content, consumed, end_of_line) = match buffer.iter().position(|b| *b == b'\n') {
+                        Some(position) => (&buffer[..position], position + 1, true),
+                        None => (buffer, buffer.len(), false),
+                    };
+
+                    let room = self
+                        .max_line_length
+                        .map(|max| max.saturating_sub(line.len()))
+                        .unwrap_or(content.len());
+                    line.extend_from_slice(&content[..content.len().min(room)]);
+
+                    (consumed, end_of_line)
+                }
+            };
+
+            reader.consume(consumed);
+
+            if end_of_line {
+                self.send_line(&line, first_line);
+                line.clear();
+                first_line = false;
             }
         }
     }
+
+    fn send_line(&self, line: &[u8], first_line: bool) {
+        let line = match line.strip_prefix(b"\xEF\xBB\xBF") {
+            Some(line) if first_line => line,
+            _ => line,
+        };
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let _ = self.sender.send(Ok(String::from_utf8_lossy(line).into_owned()));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_kills_the_whole_process_group() {
+        let _lock = crate::tests::PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let mut process = Process::new(
+            "/bin/sh",
+            std::env::temp_dir(),
+            HashMap::new(),
+            Duration::from_millis(500),
+        );
+        process.set_process_group(true);
+        let context = process
+            .spawn(["-c", "sleep 30 >/dev/null 2>&1 & exec sleep 30"])
+            .unwrap();
+        let group = context.child.id() as libc::pid_t;
+
+        assert!(matches!(context.wait(|_| {}, |_| {}), Err(Error::TimeoutError)));
+
+        // the orphaned `sleep` may take a moment to be reaped by init
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while unsafe { libc::killpg(group, 0) } == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_ne!(
+            unsafe { libc::killpg(group, 0) },
+            0,
+            "a process of the group survived the timeout"
+        );
+    }
+
+    #[cfg(feature = "ctrlc")]
+    #[test]
+    fn commands_spawned_after_an_interrupt_run_normally() {
+        let _lock = crate::tests::PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let process = Process::new("/bin/sh", std::env::temp_dir(), HashMap::new(), Duration::from_secs(10));
+
+        let context = process.spawn(["-c", "exec sleep 5"]).unwrap();
+        interrupt::request();
+        assert!(matches!(context.wait(|_| {}, |_| {}), Err(Error::Interrupted)));
+
+        let context = process.spawn(["-c", "exit 0"]).unwrap();
+        assert_eq!(context.wait(|_| {}, |_| {}).unwrap().exit_code, Some(0));
+    }
 }
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::Waker;
+
+// Tracks the commands spawned by every `Process` it is set on, so they can all be stopped at once (e.g. on service
+// shutdown). Clones share the same registry.
+#[derive(Clone, Default)]
+pub struct ProcessRegistry {
+    running: Arc<Mutex<Vec<Weak<Cancellation>>>>,
+}
+
+impl ProcessRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Interrupts every running command gracefully, as a single Ctrl-C would: Terraform stops once the current
+    // operations are over, releasing its locks and persisting its state, and `wait` returns `Error::Interrupted`.
+    // Commands spawned afterwards aren't affected. Returns the number of commands interrupted.
+    pub fn cancel_all(&self) -> usize {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        running.retain(|cancellation| cancellation.strong_count() > 0);
+
+        running
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|cancellation| cancellation.cancel())
+            .count()
+    }
+
+    // The returned cancellation is set when the command must be interrupted, and unregistered once dropped with its
+    // context.
+    pub(crate) fn register(&self) -> Arc<Cancellation> {
+        let cancellation = Arc::new(Cancellation::default());
+
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        running.retain(|cancellation| cancellation.strong_count() > 0);
+        running.push(Arc::downgrade(&cancellation));
+
+        cancellation
+    }
+}
+
+// `wait` polls the flag, async streams register their waker to be woken up on cancellation.
+#[derive(Default)]
+pub(crate) struct Cancellation {
+    cancelled: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Cancellation {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    // The waker is registered before checking the flag, so a concurrent `cancel` can't be missed.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn is_cancelled_or_wake(&self, waker: &Waker) -> bool {
+        *self.waker.lock().unwrap_or_else(|e| e.into_inner()) = Some(waker.clone());
+
+        self.is_cancelled()
+    }
+
+    // `false` when it was already cancelled.
+    fn cancel(&self) -> bool {
+        if self.cancelled.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+
+        if let Some(waker) = self.waker.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            waker.wake();
+        }
+
+        true
+    }
+}
@@ -0,0 +1,26 @@
+use crate::event::TerraformEvent;
+use std::sync::mpsc::{Sender, SyncSender};
+
+// Receives every event emitted while running commands. Sending is best effort: a sink whose receiving end is gone
+// just drops the events.
+pub trait EventSink {
+    fn send(&self, event: TerraformEvent);
+}
+
+impl EventSink for Sender<TerraformEvent> {
+    fn send(&self, event: TerraformEvent) {
+        let _ = Sender::send(self, event);
+    }
+}
+
+impl EventSink for SyncSender<TerraformEvent> {
+    fn send(&self, event: TerraformEvent) {
+        let _ = SyncSender::send(self, event);
+    }
+}
+
+impl EventSink for Box<dyn EventSink + Send + Sync> {
+    fn send(&self, event: TerraformEvent) {
+        self.as_ref().send(event)
+    }
+}
@@ -0,0 +1,296 @@
+use crate::process::{self, Cancellation};
+use crate::{ApplyMode, Error, EventSink, Terraform, TerraformEvent, TerraformResourceStatus, TerraformSourceStream};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio_stream::wrappers::SplitStream;
+use tokio_stream::{Stream, StreamExt};
+
+impl<P, Q, S> Terraform<P, Q, S>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    S: EventSink,
+{
+    // Async counterpart of `run_apply`, to be called from a tokio runtime: the events are yielded by the returned
+    // stream instead of being sent, stdout and stderr lines interleaved as they are received. The last event has the
+    // `Exited` status and the exit code of Terraform, and dropping the stream early kills Terraform.
+    // The log sink, metrics callback and registry of `Process` apply as for `wait`, and the plan is removed on failure
+    // when requested, but the timeouts don't. Approval must be automatic (`Error::ApprovalUnsupported` otherwise).
+    pub fn run_apply_stream(&self, target_plan: P) -> Result<impl Stream<Item = TerraformEvent> + '_, Error>
+    where
+        P: AsRef<Path>,
+    {
+        if self.read_only {
+            return Err(Error::OperationForbidden);
+        }
+        if !self.auto_approve {
+            return Err(Error::ApprovalUnsupported);
+        }
+
+        let command: &str = "apply";
+        let target_plan = target_plan.as_ref().to_path_buf();
+        let args = self.apply_args(
+            ApplyMode::Plan(&target_plan),
+            self.flags.input,
+            &[],
+            &self.flags,
+            self.auto_approve,
+        )?;
+
+        let mut child = tokio::process::Command::from(self.process.command(
+            self.with_default_args(args.iter().map(String::as_str).collect()),
+            &HashMap::new(),
+        ))
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(process::Error::from)?;
+        let start = Instant::now();
+
+        // unless the command configurator redirected them
+        let (stdout, stderr) = match (child.stdout.take(), child.stderr.take()) {
+            (Some(stdout), Some(stderr)) => (stdout, stderr),
+            _ => return Err(std::io::Error::other("the output of Terraform isn't piped").into()),
+        };
+
+        let mut error: Option<String> = None;
+        let stdout = lines(stdout).filter_map(move |stdout| {
+            self.process.log_line(&stdout);
+            self.event(command, stdout, TerraformSourceStream::Stdout, |stdout| {
+                self.parser.parse_apply_stdout(stdout)
+            })
+        });
+        let stderr = lines(stderr).filter_map(move |stderr| {
+            self.process.log_line(&stderr);
+            self.event(command, stderr, TerraformSourceStream::Stderr, |stderr| {
+                self.parser.parse_apply_stderr(stderr, &mut error)
+            })
+        });
+
+        let finish = move |exit_code: Option<i32>| {
+            self.process.report_metrics(command, start.elapsed(), exit_code);
+
+            let mut events = Vec::new();
+            if self.remove_plan_on_failure && exit_code != Some(0) {
+                events.extend(self.remove_failed_plan(command, &target_plan));
+            }
+            events.push(TerraformEvent {
+                status: Some(TerraformResourceStatus::Exited),
+                exit_code,
+                matched: true,
+                stack: self.stack.clone(),
+                command: String::from(command),
+                ..TerraformEvent::default()
+            });
+
+            events
+        };
+
+        Ok(EventStream {
+            events: Some(stdout.merge(stderr)),
+            pid: child.id(),
+            cancellation: self.process.register(),
+            interrupted: false,
+            exit: Box::pin(async move { child.wait().await }),
+            finish: Some(finish),
+            last: Vec::new().into_iter(),
+        })
+    }
+}
+
+// Lines are decoded lossily, as `ProcessContext::wait` does; a read error ends the stream.
+fn lines<R>(pipe: R) -> impl Stream<Item = String>
+where
+    R: AsyncRead + Unpin,
+{
+    SplitStream::new(BufReader::new(pipe).split(b'\n'))
+        .map_while(Result::ok)
+        .map(|mut line| {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            String::from_utf8_lossy(&line).into_owned()
+        })
+}
+
+// The output events, then the ones built by `finish` from the exit code once Terraform exited. The child is owned by
+// `exit`, so it is killed when the stream is dropped.
+struct EventStream<T, F> {
+    events: Option<T>,
+    pid: Option<u32>,
+    cancellation: Option<Arc<Cancellation>>,
+    interrupted: bool,
+    exit: Pin<Box<dyn Future<Output = std::io::Result<ExitStatus>> + Send>>,
+    finish: Option<F>,
+    last: std::vec::IntoIter<TerraformEvent>,
+}
+
+impl<T, F> EventStream<T, F> {
+    // Same as `ProcessContext::interrupt`: a single SIGINT on Unix, a kill elsewhere (dropping the child kills it).
+    fn interrupt(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid {
+            unsafe {
+                libc::kill(pid as libc::pid_t, libc::SIGINT);
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            self.exit = Box::pin(std::future::ready(Err(std::io::ErrorKind::Interrupted.into())));
+        }
+
+        self.interrupted = true;
+    }
+}
+
+impl<T, F> Stream for EventStream<T, F>
+where
+    T: Stream<Item = TerraformEvent> + Unpin,
+    F: FnOnce(Option<i32>) -> Vec<TerraformEvent> + Unpin,
+{
+    type Item = TerraformEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<TerraformEvent>> {
+        let this = &mut *self;
+
+        // until Terraform exited: past that point, its pid may have been reused
+        let cancelled = match &this.cancellation {
+            Some(cancellation) => cancellation.is_cancelled_or_wake(cx.waker()),
+            None => false,
+        };
+        if cancelled && !this.interrupted && this.finish.is_some() {
+            this.interrupt();
+        }
+
+        if let Some(events) = &mut this.events {
+            match Pin::new(events).poll_next(cx) {
+                Poll::Ready(None) => this.events = None,
+                poll => return poll,
+            }
+        }
+
+        if let Some(finish) = this.finish.take() {
+            match this.exit.as_mut().poll(cx) {
+                Poll::Ready(status) => this.last = finish(status.ok().and_then(|status| status.code())).into_iter(),
+                Poll::Pending => {
+                    this.finish = Some(finish);
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        Poll::Ready(this.last.next())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use crate::process::ProcessRegistry;
+    use crate::tests::{fake_terraform, PROCESSES};
+    use crate::TerraformResourceStatus;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use tokio_stream::StreamExt;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failed_apply_stream_ends_with_exit_code() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut terraform, _events) =
+            fake_terraform("apply-stream-failed", "echo 'Error: Invalid count' >&2\nexit 1\n");
+        let plan = terraform.process.working_directory().join("plan.tfplan");
+        std::fs::write(&plan, "").unwrap();
+
+        let log = SharedBuffer::default();
+        let metrics = Arc::new(Mutex::new(Vec::new()));
+        let recorded = metrics.clone();
+        terraform.set_auto_approve(true);
+        terraform.set_remove_plan_on_failure(true);
+        terraform.process.set_log_sink(Box::new(log.clone()));
+        terraform
+            .process
+            .set_metrics_callback(Some(Box::new(move |command, _, exit_code| {
+                recorded.lock().unwrap().push((String::from(command), exit_code));
+            })));
+
+        let events: Vec<_> = block_on(async {
+            let stream = terraform.run_apply_stream(PathBuf::from("plan.tfplan")).unwrap();
+            stream.collect().await
+        });
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].source, "Error: Invalid count");
+        assert_eq!(events[1].status, Some(TerraformResourceStatus::Notice));
+        assert_eq!(events[2].status, Some(TerraformResourceStatus::Exited));
+        assert_eq!(events[2].exit_code, Some(1));
+        assert!(!plan.exists());
+        assert_eq!(
+            String::from_utf8_lossy(&log.0.lock().unwrap()),
+            "Error: Invalid count\n"
+        );
+        assert_eq!(*metrics.lock().unwrap(), vec![(String::from("apply"), Some(1))]);
+    }
+
+    #[test]
+    fn registry_interrupts_apply_stream() {
+        let _lock = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut terraform, _events) = fake_terraform(
+            "apply-stream-cancelled",
+            "trap 'echo Interrupt received.; exit 130' INT\necho started\nwhile true; do sleep 0.1; done\n",
+        );
+        std::fs::write(terraform.process.working_directory().join("plan.tfplan"), "").unwrap();
+
+        let registry = ProcessRegistry::new();
+        terraform.set_auto_approve(true);
+        terraform.process.set_registry(Some(registry.clone()));
+
+        let events: Vec<_> = block_on(async {
+            let mut stream = terraform.run_apply_stream(PathBuf::from("plan.tfplan")).unwrap();
+            assert_eq!(stream.next().await.unwrap().source, "started");
+            assert_eq!(registry.cancel_all(), 1);
+
+            stream.collect().await
+        });
+        assert_eq!(events[0].source, "Interrupt received.");
+        assert_eq!(events.last().unwrap().exit_code, Some(130));
+    }
+
+    #[test]
+    fn apply_stream_requires_auto_approve() {
+        let (mut terraform, _events) = fake_terraform("apply-stream-prompt", "exit 0\n");
+        terraform.set_auto_approve(false);
+
+        assert!(matches!(
+            terraform.run_apply_stream(PathBuf::from("plan.tfplan")),
+            Err(crate::Error::ApprovalUnsupported)
+        ));
+    }
+}
@@ -0,0 +1,12 @@
+#[derive(Debug, Default)]
+pub struct TestReport {
+    pub passed: Vec<String>,
+    pub failed: Vec<(String, String)>,
+    pub exit_code: Option<i32>,
+}
+
+impl TestReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.exit_code == Some(0)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::event::{TerraformEvent, TerraformResourceStatus};
+use chrono::{DateTime, FixedOffset};
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Collects how long each resource took to apply, to find the ones dominating the apply time. Durations come from
+// the "complete after" elapsed time, or from the `Started` and `Done` timestamps in JSON mode. The operations of a
+// replaced resource (destroy and create) add up.
+#[derive(Debug, Default)]
+pub struct TimingReport {
+    started: HashMap<String, DateTime<FixedOffset>>,
+    durations: HashMap<String, Duration>,
+}
+
+impl TimingReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track(&mut self, event: &TerraformEvent) {
+        let address = match event.resource_path.as_ref() {
+            Some(address) => address,
+            None => return,
+        };
+
+        match event.status {
+            Some(TerraformResourceStatus::Started) => {
+                if let Some(timestamp) = event.timestamp {
+                    self.started.insert(address.clone(), timestamp);
+                }
+            }
+            Some(TerraformResourceStatus::Done | TerraformResourceStatus::Failed) => {
+                let started = self.started.remove(address);
+                let elapsed = event.elapsed.or_else(|| (event.timestamp? - started?).to_std().ok());
+
+                if let Some(elapsed) = elapsed {
+                    *self.durations.entry(address.clone()).or_default() += elapsed;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Resources by decreasing duration, then by address.
+    pub fn report(&self) -> Vec<(String, Duration)> {
+        let mut report: Vec<(String, Duration)> = self
+            .durations
+            .iter()
+            .map(|(address, duration)| (address.clone(), *duration))
+            .collect();
+        report.sort_by(|(a, a_duration), (b, b_duration)| b_duration.cmp(a_duration).then_with(|| a.cmp(b)));
+
+        report
+    }
+}
@@ -0,0 +1,106 @@
+use crate::event::{TerraformEvent, TerraformResourceStatus};
+use std::collections::HashMap;
+
+// Follows resources through `Started` -> `InProgress` -> `Done` (or `Failed`), counting running and done resources.
+// The total is taken from the plan summary, or set explicitly (e.g. for a destroy following an apply).
+#[derive(Debug, Default)]
+pub struct ResourceTracker {
+    statuses: HashMap<String, TerraformResourceStatus>,
+    total: u32,
+    running: u32,
+    done: u32,
+}
+
+impl ResourceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns true when the event changed one of the counters.
+    pub fn track(&mut self, event: &TerraformEvent) -> bool {
+        match (event.status, event.resource_path.as_ref()) {
+            (Some(TerraformResourceStatus::Completed), _) if event.command == "plan" => {
+                self.total =
+                    event.create_count.unwrap_or(0) + event.update_count.unwrap_or(0) + event.delete_count.unwrap_or(0);
+                true
+            }
+            (Some(status @ TerraformResourceStatus::Started), Some(address)) => {
+                self.running += 1;
+                self.statuses.insert(address.clone(), status);
+                true
+            }
+            (Some(status @ TerraformResourceStatus::InProgress), Some(address)) => {
+                self.statuses.insert(address.clone(), status);
+                false
+            }
+            (Some(status @ TerraformResourceStatus::Done), Some(address)) => {
+                if let Some(TerraformResourceStatus::Started | TerraformResourceStatus::InProgress) =
+                    self.statuses.insert(address.clone(), status)
+                {
+                    self.running -= 1;
+                }
+                self.done += 1;
+                true
+            }
+            (Some(status @ TerraformResourceStatus::Failed), Some(address)) => {
+                if let Some(TerraformResourceStatus::Started | TerraformResourceStatus::InProgress) =
+                    self.statuses.insert(address.clone(), status)
+                {
+                    self.running -= 1;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn set_total(&mut self, total: u32) {
+        self.total = total;
+    }
+
+    pub fn status(&self, address: &str) -> Option<TerraformResourceStatus> {
+        self.statuses.get(address).copied()
+    }
+
+    pub fn statuses(&self) -> &HashMap<String, TerraformResourceStatus> {
+        &self.statuses
+    }
+
+    // Addresses of the resources that reached `Done`, sorted.
+    pub fn done_addresses(&self) -> Vec<String> {
+        self.addresses(|status| status == TerraformResourceStatus::Done)
+    }
+
+    // Addresses of the resources that started but never reached `Done` (still running or failed): after a failed
+    // apply, their real state is unknown. Sorted.
+    pub fn unfinished_addresses(&self) -> Vec<String> {
+        self.addresses(|status| status != TerraformResourceStatus::Done)
+    }
+
+    fn addresses<F>(&self, filter: F) -> Vec<String>
+    where
+        F: Fn(TerraformResourceStatus) -> bool,
+    {
+        let mut addresses: Vec<String> = self
+            .statuses
+            .iter()
+            .filter(|(_, status)| filter(**status))
+            .map(|(address, _)| address.clone())
+            .collect();
+        addresses.sort();
+
+        addresses
+    }
+
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn running(&self) -> u32 {
+        self.running
+    }
+
+    pub fn done(&self) -> u32 {
+        self.done
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// A temporary variables (or backend configuration) file only readable by the current user, removed when dropped.
+pub(crate) struct VarFile {
+    path: PathBuf,
+}
+
+impl VarFile {
+    pub(crate) fn create(content: &str) -> Result<Self, std::io::Error> {
+        Self::create_with_extension(content, "tfvars.json")
+    }
+
+    // Terraform picks the syntax of the file (HCL or JSON) from its extension.
+    pub(crate) fn create_with_extension(content: &str, extension: &str) -> Result<Self, std::io::Error> {
+        let path = std::env::temp_dir().join(format!(
+            "terraform-rs-{}-{}.{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            extension
+        ));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(&path)?;
+        // the guard exists before writing, so a failed write doesn't leave the file behind
+        let var_file = Self { path };
+        file.write_all(content.as_bytes())?;
+
+        Ok(var_file)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for VarFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Writes the settings as a backend configuration file for `-backend-config`, so secrets (e.g. access keys) don't
+// appear in the command line. Values are HCL strings, escaped so they are taken literally.
+pub(crate) fn prepare_backend_config(config: &HashMap<String, String>) -> Result<VarFile, std::io::Error> {
+    let mut keys: Vec<&String> = config.keys().collect();
+    keys.sort();
+
+    let content: String = keys
+        .into_iter()
+        .map(|key| {
+            let value = config[key]
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+                .replace('\t', "\\t")
+                .replace("${", "$${")
+                .replace("%{", "%%{");
+
+            format!("{} = \"{}\"\n", key, value)
+        })
+        .collect();
+
+    VarFile::create_with_extension(&content, "backend.hcl")
+}
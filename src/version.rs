@@ -0,0 +1,56 @@
+use std::cmp::Ordering;
+
+// A "major.minor.patch" version; missing parts are zeros and a pre-release suffix ("-beta1") is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Version {
+    parts: [u64; 3],
+}
+
+impl Version {
+    pub(crate) fn parse(version: &str) -> Option<Self> {
+        let version = version.trim().trim_start_matches('v');
+        let version = version.split(['-', '+']).next()?;
+
+        let mut parts = [0; 3];
+        for (index, part) in version.split('.').enumerate() {
+            *parts.get_mut(index)? = part.parse().ok()?;
+        }
+
+        Some(Self { parts })
+    }
+}
+
+// Checks `version` against a Terraform version constraint ("~> 1.5", ">= 1.3.0, < 2.0.0", ...). Returns `None` when
+// the constraint can't be parsed.
+pub(crate) fn satisfies(version: Version, constraint: &str) -> Option<bool> {
+    for condition in constraint.split(',') {
+        let condition = condition.trim();
+        let operator_length = condition
+            .find(|c: char| c.is_ascii_digit() || c == 'v')
+            .unwrap_or(condition.len());
+        let (operator, required) = condition.split_at(operator_length);
+        let required_parts = required.trim().split('.').count();
+        let required = Version::parse(required)?;
+
+        let satisfied = match operator.trim() {
+            "" | "=" => version == required,
+            "!=" => version != required,
+            ">" => version > required,
+            ">=" => version >= required,
+            "<" => version < required,
+            "<=" => version <= required,
+            // only the rightmost given part may increase: "~> 1.2" is ">= 1.2, < 2.0", "~> 1.2.3" is ">= 1.2.3, < 1.3"
+            "~>" => {
+                let fixed = required_parts.saturating_sub(1).max(1);
+                version >= required && version.parts[..fixed].cmp(&required.parts[..fixed]) == Ordering::Equal
+            }
+            _ => return None,
+        };
+
+        if !satisfied {
+            return Some(false);
+        }
+    }
+
+    Some(true)
+}